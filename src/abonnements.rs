@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use chrono_tz::Europe::Paris;
+use poise::ChoiceParameter;
+use serenity::all::{CreateMessage, UserId};
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader, yaml};
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::audit::{Action, Evenement};
+use crate::config;
+use crate::discord;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+use crate::stockage;
+
+/// Intervalle entre deux vérifications des fins de vote et des notifications retenues.
+const INTERVALLE: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Abonnements, chargés depuis leur fichier au premier accès.
+static ABONNEMENTS: Mutex<Option<Abonnements>> = Mutex::new(None);
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Abonnement {
+    Fil(u64),
+    Pole(Pole)
+}
+
+/// Façon dont un abonné reçoit ses notifications.
+#[derive(Clone, Copy, PartialEq, Eq, ChoiceParameter, Debug)]
+pub enum Mode {
+    #[name = "Message privé"]
+    Prive,
+    #[name = "Mention dans le salon des notifications"]
+    Salon
+}
+
+/// Heures de silence, à l’heure de Paris, pendant lesquelles les notifications sont retenues.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Silence {
+    pub debut: NaiveTime,
+    pub fin: NaiveTime
+}
+
+impl Silence {
+    /// Lit une plage `HH:MM-HH:MM`, qui peut passer minuit.
+    pub fn lire(texte: &str) -> Result<Self, String> {
+        let erreur = || format!("Plage « {texte} » invalide : utilisez le format HH:MM-HH:MM.");
+        let (debut, fin) = texte.split_once('-').ok_or_else(erreur)?;
+        let heure = |heure: &str| NaiveTime::parse_from_str(heure.trim(), "%H:%M").map_err(|_| erreur());
+        Ok(Self { debut: heure(debut)?, fin: heure(fin)? })
+    }
+
+    pub fn contient(&self, date: DateTime<Utc>) -> bool {
+        let heure = date.with_timezone(&Paris).time();
+        if self.debut <= self.fin {
+            self.debut <= heure && heure < self.fin
+        } else {
+            heure >= self.debut || heure < self.fin
+        }
+    }
+}
+
+impl Display for Silence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.debut.format("%H:%M"), self.fin.format("%H:%M"))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Abonne {
+    /// Nom Discord, pour ne pas notifier un membre de ses propres changements.
+    pub nom: String,
+    pub mode: Mode,
+    pub silence: Option<Silence>,
+    pub abonnements: Vec<Abonnement>,
+    /// Notifications pas encore envoyées, retenues pendant les heures de silence.
+    en_attente: Vec<String>
+}
+
+fn cle(s: &str) -> Yaml {
+    Yaml::String(s.to_string())
+}
+
+impl Abonne {
+    pub fn new(nom: String) -> Self {
+        Self {
+            nom,
+            mode: Mode::Prive,
+            silence: None,
+            abonnements: Vec::new(),
+            en_attente: Vec::new()
+        }
+    }
+
+    fn concerne(&self, fil: &Fil) -> bool {
+        self.abonnements.iter().any(|abonnement| match abonnement {
+            Abonnement::Fil(id) => *id == fil.get_id(),
+            Abonnement::Pole(pole) => *pole == fil.pole
+        })
+    }
+
+    fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
+        let mut abonnements: Vec<Abonnement> = data["fils"].as_vec().map(|fils| fils.iter()
+            .filter_map(|id| id.as_i64().and_then(|id| id.try_into().ok()).map(Abonnement::Fil)).collect()).unwrap_or_default();
+        for pole in data["poles"].as_vec().map(Vec::as_slice).unwrap_or_default() {
+            abonnements.push(Abonnement::Pole(Pole::from_str(pole.as_str().ok_or(ErrType::NoneError)?)?));
+        }
+        Ok(Self {
+            nom: data["nom"].as_str().unwrap_or_default().to_string(),
+            mode: if data["mode"].as_str() == Some("salon") { Mode::Salon } else { Mode::Prive },
+            silence: data["silence"].as_str().map(Silence::lire).transpose().map_err(ErrType::YamlParseError)?,
+            abonnements,
+            en_attente: data["en_attente"].as_vec().map(|messages| messages.iter()
+                .filter_map(|message| message.as_str().map(str::to_string)).collect()).unwrap_or_default()
+        })
+    }
+
+    fn serialize(&self, id: u64) -> Yaml {
+        let mut yaml_out = yaml::Hash::new();
+        yaml_out.insert(cle("id"), Yaml::Integer(id as i64));
+        yaml_out.insert(cle("nom"), Yaml::String(self.nom.clone()));
+        yaml_out.insert(cle("mode"), cle(match self.mode {
+            Mode::Prive => "prive",
+            Mode::Salon => "salon"
+        }));
+        if let Some(silence) = &self.silence {
+            yaml_out.insert(cle("silence"), Yaml::String(silence.to_string()));
+        }
+        yaml_out.insert(cle("fils"), Yaml::Array(self.abonnements.iter().filter_map(|abonnement| match abonnement {
+            Abonnement::Fil(id) => Some(Yaml::Integer(*id as i64)),
+            Abonnement::Pole(_) => None
+        }).collect()));
+        yaml_out.insert(cle("poles"), Yaml::Array(self.abonnements.iter().filter_map(|abonnement| match abonnement {
+            Abonnement::Pole(pole) => Some(Yaml::String(pole.to_string())),
+            Abonnement::Fil(_) => None
+        }).collect()));
+        yaml_out.insert(cle("en_attente"), Yaml::Array(self.en_attente.iter().cloned().map(Yaml::String).collect()));
+        Yaml::Hash(yaml_out)
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Abonnements {
+    /// Abonnés, par identifiant Discord.
+    pub abonnes: HashMap<u64, Abonne>,
+    /// Échéance des votes dont la fin a déjà été rappelée, par fil.
+    rappels: HashMap<u64, i64>
+}
+
+impl Abonnements {
+    fn charger(chemin: &str) -> Result<Self, ErrType> {
+        if !Path::new(chemin).exists() {
+            return Ok(Self::default());
+        }
+        let documents = YamlLoader::load_from_str(fs::read_to_string(chemin)?.as_str())
+            .map_err(|e| ErrType::YamlParseError(e.to_string()))?;
+        let data = documents.first().unwrap_or(&Yaml::Null);
+        let mut abonnements = Self::default();
+        for abonne in data["abonnes"].as_vec().map(Vec::as_slice).unwrap_or_default() {
+            let id = abonne["id"].as_i64().and_then(|id| id.try_into().ok())
+                .ok_or(ErrType::YamlParseError("Abonné sans identifiant.".to_string()))?;
+            abonnements.abonnes.insert(id, Abonne::from_yaml(abonne)?);
+        }
+        for rappel in data["rappels"].as_vec().map(Vec::as_slice).unwrap_or_default() {
+            if let (Some(fil), Some(echeance)) = (rappel["fil"].as_i64().and_then(|id| id.try_into().ok()), rappel["echeance"].as_i64()) {
+                abonnements.rappels.insert(fil, echeance);
+            }
+        }
+        Ok(abonnements)
+    }
+
+    fn enregistrer(&self, chemin: &str) -> Result<(), ErrType> {
+        let mut abonnes: Vec<(&u64, &Abonne)> = self.abonnes.iter().collect();
+        abonnes.sort_by_key(|(id, _)| **id);
+        let mut rappels: Vec<(&u64, &i64)> = self.rappels.iter().collect();
+        rappels.sort();
+        let mut data = yaml::Hash::new();
+        data.insert(cle("abonnes"), Yaml::Array(abonnes.into_iter().map(|(id, abonne)| abonne.serialize(*id)).collect()));
+        data.insert(cle("rappels"), Yaml::Array(rappels.into_iter().map(|(fil, echeance)| {
+            let mut rappel = yaml::Hash::new();
+            rappel.insert(cle("fil"), Yaml::Integer(*fil as i64));
+            rappel.insert(cle("echeance"), Yaml::Integer(*echeance));
+            Yaml::Hash(rappel)
+        }).collect()));
+        let mut contenu = String::new();
+        YamlEmitter::new(&mut contenu).dump(&Yaml::Hash(data)).map_err(|e| ErrType::YamlParseError(e.to_string()))?;
+        stockage::ecrire_atomique(Path::new(chemin), (contenu + "\n").as_bytes())
+    }
+
+    /// Ajoute une notification à chaque abonné concerné par le fil, hormis l’auteur du changement.
+    fn notifier(&mut self, fil: &Fil, acteur: Option<&str>, message: &str) {
+        for abonne in self.abonnes.values_mut() {
+            if abonne.concerne(fil) && acteur != Some(abonne.nom.as_str()) {
+                abonne.en_attente.push(message.to_string());
+            }
+        }
+    }
+
+    /// Avertit les abonnés des votes se terminant dans moins de `rappel` heures, une fois par échéance.
+    fn rappeler<'a>(&mut self, fils: impl Iterator<Item = &'a Fil>, maintenant: DateTime<Utc>) {
+        let limite = maintenant + Duration::hours(config::get().notifications.rappel);
+        let mut votes = HashMap::new();
+        for fil in fils.filter(|fil| fil.status == Status::Vote) {
+            let Some(echeance) = fil.echeance.map(|echeance| echeance.unix_timestamp()) else {
+                continue;
+            };
+            votes.insert(fil.get_id(), echeance);
+            if echeance > maintenant.timestamp() && echeance <= limite.timestamp() && self.rappels.get(&fil.get_id()) != Some(&echeance) {
+                self.notifier(fil, None, format!("Le vote sur [{}](<{}>) se termine <t:{echeance}:R>.", fil.get_name(), fil.get_lien()).as_str());
+                self.rappels.insert(fil.get_id(), echeance);
+            }
+        }
+        /* Les votes terminés ou dont l’échéance a changé pourront être rappelés à nouveau. */
+        self.rappels.retain(|id, echeance| votes.get(id) == Some(echeance));
+    }
+
+    /// Retire les notifications des abonnés hors de leurs heures de silence, pour les envoyer.
+    fn a_envoyer(&mut self, maintenant: DateTime<Utc>) -> Vec<(u64, Mode, Vec<String>)> {
+        self.abonnes.iter_mut()
+            .filter(|(_, abonne)| !abonne.en_attente.is_empty() && !abonne.silence.is_some_and(|silence| silence.contient(maintenant)))
+            .map(|(id, abonne)| (*id, abonne.mode, std::mem::take(&mut abonne.en_attente)))
+            .collect()
+    }
+
+    /// Remet en tête des notifications de l’abonné celles qui n’ont pu être envoyées.
+    fn remettre(&mut self, id: u64, messages: Vec<String>) {
+        if let Some(abonne) = self.abonnes.get_mut(&id) {
+            abonne.en_attente.splice(0..0, messages);
+        }
+    }
+}
+
+/// Donne accès aux abonnements, enregistrés ensuite s’ils ont été modifiés.
+pub fn acceder<R>(f: impl FnOnce(&mut Abonnements) -> R) -> Result<R, ErrType> {
+    let chemin = config::get().notifications.fichier.as_str();
+    let mut abonnements = ABONNEMENTS.lock().unwrap();
+    if abonnements.is_none() {
+        *abonnements = Some(Abonnements::charger(chemin)?);
+    }
+    let abonnements = abonnements.as_mut().unwrap();
+    let avant = abonnements.clone();
+    let resultat = f(abonnements);
+    if *abonnements != avant {
+        abonnements.enregistrer(chemin)?;
+    }
+    Ok(resultat)
+}
+
+/// Notifie les abonnés des changements de statut et des notes, puis envoie les notifications.
+pub fn repercuter(evenements: &[Evenement]) {
+    let messages: Vec<(&Fil, &str, String)> = evenements.iter().filter_map(|evenement| {
+        let fil = &evenement.fil;
+        let message = match &evenement.action {
+            Action::Statut(avant, apres) => format!("[{}](<{}>) : {avant} → {apres} (par {}).", fil.get_name(), fil.get_lien(), evenement.acteur),
+            Action::Note(texte) => format!("Note de {} sur [{}](<{}>) : {texte}", evenement.acteur, fil.get_name(), fil.get_lien()),
+            _ => return None
+        };
+        Some((fil, evenement.acteur.as_str(), message))
+    }).collect();
+    if messages.is_empty() {
+        return;
+    }
+    if let Err(e) = acceder(|abonnements| for (fil, acteur, message) in &messages {
+        abonnements.notifier(fil, Some(acteur), message);
+    }) {
+        tracing::error!(erreur = %e, "Erreur lors de l’enregistrement des notifications");
+    }
+    tokio::spawn(envoyer());
+}
+
+/// Regroupe des notifications en messages Discord de taille acceptable.
+fn paquets(messages: &[String]) -> Vec<String> {
+    let mut paquets: Vec<String> = Vec::new();
+    for message in messages {
+        match paquets.last_mut() {
+            Some(paquet) if paquet.len() + message.len() < 1900 => {
+                paquet.push('\n');
+                paquet.push_str(message);
+            }
+            _ => paquets.push(message.chars().take(1900).collect())
+        }
+    }
+    paquets
+}
+
+async fn envoyer() {
+    if discord::http().is_none() {
+        return;
+    }
+    let a_envoyer = match acceder(|abonnements| abonnements.a_envoyer(Utc::now())) {
+        Ok(a_envoyer) => a_envoyer,
+        Err(e) => {
+            tracing::error!(erreur = %e, "Erreur lors de la lecture des abonnements");
+            return;
+        }
+    };
+    let salon = config::get().notifications.salon;
+    for (id, mode, messages) in a_envoyer {
+        let mut paquets = paquets(&messages).into_iter();
+        while let Some(paquet) = paquets.next() {
+            if let Err(e) = envoyer_paquet(id, mode, salon, paquet.as_str()).await {
+                /* Les notifications non envoyées sont gardées pour la prochaine tentative. */
+                tracing::error!(erreur = %e, abonne = id, "Erreur lors de l’envoi d’une notification, nouvel essai plus tard");
+                let restants: Vec<String> = std::iter::once(paquet).chain(paquets).collect();
+                if let Err(e) = acceder(|abonnements| abonnements.remettre(id, restants)) {
+                    tracing::error!(erreur = %e, abonne = id, "Notifications non envoyées perdues");
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// Envoie un paquet de notifications à un abonné : en message privé, ou par mention dans le salon des
+/// notifications si c’est son choix ou si son message privé échoue (messages privés fermés…).
+async fn envoyer_paquet(id: u64, mode: Mode, salon: Option<u64>, paquet: &str) -> Result<(), ErrType> {
+    let mentionner = |salon: u64| discord::envoyer(salon, CreateMessage::new().content(format!("<@{id}>\n{paquet}")));
+    if let (Mode::Salon, Some(salon)) = (mode, salon) {
+        return mentionner(salon).await.map(|_| ());
+    }
+    let Some(http) = discord::http() else {
+        return Ok(());
+    };
+    match UserId::new(id).direct_message(&http, CreateMessage::new().content(paquet)).await {
+        Ok(_) => Ok(()),
+        Err(e) => match salon {
+            Some(salon) => {
+                tracing::warn!(erreur = %e, abonne = id, "Message privé refusé, notification envoyée dans le salon");
+                mentionner(salon).await.map(|_| ())
+            }
+            None => Err(e.into())
+        }
+    }
+}
+
+/// Rappelle les fins de vote et envoie les notifications retenues pendant les heures de silence.
+/// Ne rend jamais la main.
+pub async fn surveiller() {
+    let bot = stockage::attendre().await;
+    loop {
+        tokio::time::sleep(INTERVALLE).await;
+        {
+            let bot = bot.lock().await;
+            if let Err(e) = acceder(|abonnements| abonnements.rappeler(bot.database.values(), Utc::now())) {
+                tracing::error!(erreur = %e, "Erreur lors du rappel des fins de vote");
+            }
+        }
+        envoyer().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn silence() {
+        let nuit = Silence::lire("22:00-07:30").unwrap();
+        /* 23 h et 7 h à Paris, en été. */
+        assert!(nuit.contient(Utc.with_ymd_and_hms(2024, 7, 1, 21, 0, 0).unwrap()));
+        assert!(nuit.contient(Utc.with_ymd_and_hms(2024, 7, 1, 5, 0, 0).unwrap()));
+        assert!(!nuit.contient(Utc.with_ymd_and_hms(2024, 7, 1, 6, 0, 0).unwrap()));
+        assert_eq!(nuit.to_string(), "22:00-07:30");
+        assert!(Silence::lire("22h-7h").is_err());
+    }
+
+    #[test]
+    fn rappel_des_votes() {
+        let fils = stockage::tests::fixture("v2.yml");
+        let mut abonnements = Abonnements::default();
+        let mut alice = Abonne::new("alice".to_string());
+        alice.abonnements.push(Abonnement::Pole(Pole::Technique));
+        let mut bob = Abonne::new("bob".to_string());
+        bob.abonnements.push(Abonnement::Fil(1234567));
+        bob.silence = Some(Silence::lire("22:00-07:00").unwrap());
+        abonnements.abonnes.insert(1, alice);
+        abonnements.abonnes.insert(2, bob);
+
+        /* Deux heures avant la fin du vote (1721600000), soit 20 h 13 UTC et 22 h 13 à Paris : bob est en silence. */
+        let date = DateTime::from_timestamp(1721600000 - 7200, 0).unwrap();
+        abonnements.rappeler(fils.iter(), date);
+        abonnements.rappeler(fils.iter(), date);
+        let envois = abonnements.a_envoyer(date);
+        assert_eq!(envois.len(), 1);
+        assert_eq!(envois[0].0, 1);
+        assert_eq!(envois[0].2.len(), 1);
+        assert!(envois[0].2[0].starts_with("Le vote sur [Refonte du guide]"));
+        assert_eq!(abonnements.abonnes[&2].en_attente.len(), 1);
+
+        /* Les notifications d’un changement ne sont pas envoyées à son auteur. */
+        abonnements.notifier(&fils[0], Some("bob"), "Changement");
+        assert_eq!(abonnements.abonnes[&2].en_attente.len(), 1);
+
+        /* Un envoi échoué est remis avant les notifications arrivées entre-temps. */
+        abonnements.remettre(1, envois[0].2.clone());
+        abonnements.notifier(&fils[0], None, "Changement");
+        assert_eq!(abonnements.abonnes[&1].en_attente[0], envois[0].2[0]);
+        assert_eq!(abonnements.abonnes[&1].en_attente[1], "Changement");
+    }
+}
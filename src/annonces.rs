@@ -0,0 +1,155 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serenity::all::{CreateAllowedMentions, CreateMessage, RoleId};
+
+use fondabots_lib::Object;
+
+use crate::audit::{Action, Evenement};
+use crate::config;
+use crate::config::Annonces;
+use crate::discord;
+use crate::fil::Fil;
+
+/// Date des dernières annonces détaillées, par salon, pour la limite d’annonces.
+static HISTORIQUE: Mutex<Option<HashMap<u64, VecDeque<i64>>>> = Mutex::new(None);
+
+#[derive(Debug)]
+struct Annonce {
+    salon: u64,
+    role: Option<u64>,
+    texte: String,
+    fil: Fil
+}
+
+impl Annonce {
+    fn message(&self) -> CreateMessage {
+        let message = CreateMessage::new().embed(self.fil.get_embed());
+        match self.role {
+            Some(role) => message.content(format!("<@&{role}> {}", self.texte))
+                .allowed_mentions(CreateAllowedMentions::new().roles(vec![RoleId::new(role)])),
+            None => message.content(self.texte.clone()).allowed_mentions(CreateAllowedMentions::new())
+        }
+    }
+}
+
+/// Annonces à faire pour les changements constatés, selon la configuration de chaque pôle.
+fn preparer(evenements: &[Evenement], config: &Annonces) -> Vec<Annonce> {
+    evenements.iter().filter_map(|evenement| {
+        let fil = &evenement.fil;
+        let annonces = config.pole(&fil.pole)?;
+        let texte = match &evenement.action {
+            Action::Ajout if annonces.nouveaux => format!("Nouveau fil dans le pôle {} : « {} ».", fil.pole, fil.get_name()),
+            Action::Statut(_, _) if annonces.statuts.contains(&fil.status) => format!("« {} » passe au statut {}.", fil.get_name(), fil.status),
+            _ => return None
+        };
+        Some(Annonce { salon: annonces.salon, role: annonces.role, texte, fil: fil.clone() })
+    }).collect()
+}
+
+/// Sépare les annonces à détailler, dans la limite fixée pour chaque salon, de celles à regrouper
+/// dans un récapitulatif.
+fn repartir(annonces: Vec<Annonce>, historique: &mut HashMap<u64, VecDeque<i64>>, maintenant: i64, config: &Annonces) -> (Vec<Annonce>, Vec<Annonce>) {
+    let (mut detaillees, mut regroupees) = (Vec::new(), Vec::new());
+    for annonce in annonces {
+        let dates = historique.entry(annonce.salon).or_default();
+        while dates.front().is_some_and(|date| *date <= maintenant - config.periode * 60) {
+            dates.pop_front();
+        }
+        if dates.len() < config.limite {
+            dates.push_back(maintenant);
+            detaillees.push(annonce);
+        } else {
+            regroupees.push(annonce);
+        }
+    }
+    (detaillees, regroupees)
+}
+
+/// Récapitulatif des annonces d’un salon qui n’ont pas été détaillées.
+fn recapitulatif(annonces: &[&Annonce]) -> String {
+    let mut texte = format!("{} autre(s) annonce(s), regroupée(s) pour ne pas inonder le salon :", annonces.len());
+    for (i, annonce) in annonces.iter().enumerate() {
+        let ligne = format!("\n- [{}](<{}>) : {}", annonce.fil.get_name(), annonce.fil.get_lien(), annonce.fil.status);
+        if texte.len() + ligne.len() > 1900 {
+            texte += format!("\n… et {} autre(s).", annonces.len() - i).as_str();
+            break;
+        }
+        texte += ligne.as_str();
+    }
+    texte
+}
+
+async fn envoyer(detaillees: Vec<Annonce>, regroupees: Vec<Annonce>) {
+    for annonce in &detaillees {
+        if let Err(e) = discord::envoyer(annonce.salon, annonce.message()).await {
+            tracing::error!(erreur = %e, salon = annonce.salon, fil = annonce.fil.get_id(), "Erreur lors de l’envoi d’une annonce");
+        }
+    }
+    let mut par_salon: Vec<(u64, Vec<&Annonce>)> = Vec::new();
+    for annonce in &regroupees {
+        match par_salon.iter_mut().find(|(salon, _)| *salon == annonce.salon) {
+            Some((_, annonces)) => annonces.push(annonce),
+            None => par_salon.push((annonce.salon, vec![annonce]))
+        }
+    }
+    for (salon, annonces) in par_salon {
+        let message = CreateMessage::new().content(recapitulatif(&annonces)).allowed_mentions(CreateAllowedMentions::new());
+        if let Err(e) = discord::envoyer(salon, message).await {
+            tracing::error!(erreur = %e, salon, "Erreur lors de l’envoi du récapitulatif des annonces");
+        }
+    }
+}
+
+/// Annonce les nouveaux fils et les changements de statut dans le salon de leur pôle, s’il est configuré.
+pub fn repercuter(evenements: &[Evenement]) {
+    let config = &config::get().annonces;
+    let annonces = preparer(evenements, config);
+    if annonces.is_empty() {
+        return;
+    }
+    let (detaillees, regroupees) = repartir(annonces, HISTORIQUE.lock().unwrap().get_or_insert_default(),
+                                            chrono::Utc::now().timestamp(), config);
+    tokio::spawn(envoyer(detaillees, regroupees));
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::Timestamp;
+
+    use crate::config::AnnoncesPole;
+    use crate::fil::fields::{Pole, Status};
+    use crate::stockage;
+
+    use super::*;
+
+    #[test]
+    fn limite_par_salon() {
+        let fils = stockage::tests::fixture("v2.yml");
+        let config = Annonces {
+            poles: vec![(Pole::Technique, AnnoncesPole { salon: 1, role: Some(2), nouveaux: true, statuts: vec![Status::Vote] })],
+            limite: 2,
+            periode: 10
+        };
+        let evenement = |fil: &Fil, action: Action| Evenement { date: Timestamp::now(), acteur: "Flux RSS".to_string(), fil: fil.clone(), action };
+        /* Refonte du guide (Technique, au vote) et la charte (Légal, pôle non configuré). */
+        let evenements = vec![
+            evenement(&fils[0], Action::Ajout),
+            evenement(&fils[0], Action::Statut("Discussion".to_string(), "Vote".to_string())),
+            evenement(&fils[1], Action::Ajout),
+            evenement(&fils[2], Action::Ajout),
+            evenement(&fils[2], Action::Note("Rien à annoncer".to_string()))
+        ];
+        let annonces = preparer(&evenements, &config);
+        assert_eq!(annonces.len(), 3);
+        assert_eq!(annonces[1].texte, "« Refonte du guide » passe au statut Vote.");
+
+        let mut historique = HashMap::new();
+        let (detaillees, regroupees) = repartir(annonces, &mut historique, 1000, &config);
+        assert_eq!((detaillees.len(), regroupees.len()), (2, 1));
+        assert!(recapitulatif(&regroupees.iter().collect::<Vec<&Annonce>>()).contains("[Nouvelles règles du chat]"));
+        /* Une fois la période écoulée, les annonces sont de nouveau détaillées. */
+        let (detaillees, _) = repartir(preparer(&evenements, &config), &mut historique, 1000 + 600, &config);
+        assert_eq!(detaillees.len(), 2);
+    }
+}
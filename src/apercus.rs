@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serenity::all::{Context, CreateAllowedMentions, CreateMessage, Message};
+
+use fondabots_lib::Object;
+
+use crate::config;
+use crate::fil::Fil;
+use crate::liens;
+use crate::stockage;
+
+/// Nombre maximal d’aperçus envoyés en réponse à un même message.
+const MAXIMUM: usize = 5;
+
+/// Date du dernier aperçu de chaque fil, par salon et fil.
+static DERNIERS: Mutex<Option<HashMap<(u64, u64), i64>>> = Mutex::new(None);
+
+/// Fils dont l’aperçu est à envoyer dans le salon : ceux qui n’en ont pas eu depuis `delai` minutes.
+fn a_envoyer(derniers: &mut HashMap<(u64, u64), i64>, salon: u64, ids: &[u64], maintenant: i64, delai: i64) -> Vec<u64> {
+    derniers.retain(|_, date| *date > maintenant - delai * 60);
+    ids.iter().copied().filter(|id| {
+        let nouveau = !derniers.contains_key(&(salon, *id));
+        if nouveau {
+            derniers.insert((salon, *id), maintenant);
+        }
+        nouveau
+    }).take(MAXIMUM).collect()
+}
+
+/// Le message a-t-il été posté par un membre dans l’un des salons des aperçus ?
+pub fn concerne(message: &Message) -> bool {
+    !message.author.bot && config::get().apercus.salons.contains(&message.channel_id.get())
+}
+
+/// Répond à un message par l’aperçu des fils qu’il cite, reçu par la connexion du bot à la passerelle.
+pub async fn repondre(ctx: Context, message: Message) {
+    let config = &config::get().apercus;
+    let liens = liens::extraire(message.content.as_str());
+    let Some(bot) = stockage::bot().filter(|_| !liens.is_empty()) else {
+        return;
+    };
+    let fils: Vec<Fil> = {
+        let bot = bot.lock().await;
+        liens.iter().filter_map(|(id, _)| bot.database.get(id).cloned()).collect()
+    };
+    let ids: Vec<u64> = fils.iter().map(Fil::get_id).collect();
+    let ids = a_envoyer(DERNIERS.lock().unwrap().get_or_insert_default(), message.channel_id.get(), &ids,
+                        chrono::Utc::now().timestamp(), config.delai);
+    for fil in fils.iter().filter(|fil| ids.contains(&fil.get_id())) {
+        let apercu = CreateMessage::new().embed(fil.get_embed()).components(vec![fil.get_buttons()])
+            .reference_message(&message).allowed_mentions(CreateAllowedMentions::new());
+        if let Err(e) = message.channel_id.send_message(&ctx, apercu).await {
+            tracing::error!(erreur = %e, salon = message.channel_id.get(), fil = fil.get_id(), "Erreur lors de l’envoi d’un aperçu");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn un_apercu_par_delai() {
+        let mut derniers = HashMap::new();
+        assert_eq!(a_envoyer(&mut derniers, 1, &[10, 20], 1000, 60), vec![10, 20]);
+        /* Même salon : déjà envoyés ; autre salon : à envoyer. */
+        assert!(a_envoyer(&mut derniers, 1, &[10], 1000 + 60, 60).is_empty());
+        assert_eq!(a_envoyer(&mut derniers, 2, &[10], 1000 + 60, 60), vec![10]);
+        /* Une heure plus tard, le fil peut de nouveau être présenté. */
+        assert_eq!(a_envoyer(&mut derniers, 1, &[10], 1000 + 3600, 60), vec![10]);
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use fondabots_lib::object::Field;
+use fondabots_lib::{ErrType, Object};
+
+use crate::calendrier;
+use crate::export;
+use crate::flux;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+use crate::stockage;
+
+/// Origine des fils servis par l’API.
+#[derive(Clone, Debug)]
+pub enum Source {
+    /// Base de données du bot en cours d’exécution.
+    Bot,
+    /// Fichier de base de données, relu à chaque requête (`staffbot servir`).
+    Fichier(String)
+}
+
+impl Source {
+    async fn fils(&self) -> Result<Vec<Fil>, Erreur> {
+        match self {
+            Source::Bot => match stockage::bot() {
+                Some(bot) => Ok(bot.lock().await.database.values().cloned().collect()),
+                None => Err(Erreur(StatusCode::SERVICE_UNAVAILABLE, "La base de données n’est pas encore chargée.".to_string()))
+            },
+            Source::Fichier(chemin) => stockage::charger(chemin.as_str())
+                .map_err(|e| Erreur(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+struct Etat {
+    source: Source,
+    jeton: Option<String>
+}
+
+/// Erreur renvoyée au client sous la forme `{"erreur": "…"}`.
+struct Erreur(StatusCode, String);
+
+impl IntoResponse for Erreur {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "erreur": self.1 }))).into_response()
+    }
+}
+
+/// Vérifie le jeton, fourni dans l’en-tête `Authorization: Bearer …` ou, pour les lecteurs de flux
+/// qui ne savent pas ajouter d’en-tête, dans le paramètre `jeton` de l’adresse.
+async fn authentifier(State(etat): State<Arc<Etat>>, Query(parametres): Query<HashMap<String, String>>,
+                      requete: Request, suite: Next) -> Response {
+    if let Some(jeton) = &etat.jeton {
+        let fourni = requete.headers().get(header::AUTHORIZATION)
+            .and_then(|valeur| valeur.to_str().ok())
+            .and_then(|valeur| valeur.strip_prefix("Bearer "))
+            .or(parametres.get("jeton").map(String::as_str));
+        if fourni != Some(jeton.as_str()) {
+            return Erreur(StatusCode::UNAUTHORIZED, "Jeton absent ou invalide.".to_string()).into_response();
+        }
+    }
+    suite.run(requete).await
+}
+
+/// `GET /fils[?pole=P][&statut=S]`
+async fn lister(State(etat): State<Arc<Etat>>, Query(filtres): Query<HashMap<String, String>>) -> Result<Json<Value>, Erreur> {
+    let filtre = |nom: &str| filtres.get(nom).map(String::as_str);
+    let invalide = |e: ErrType| Erreur(StatusCode::BAD_REQUEST, e.to_string());
+    let pole = filtre("pole").map(Pole::from_str).transpose().map_err(invalide)?;
+    let statut = filtre("statut").map(Status::from_str).transpose().map_err(invalide)?;
+    let fils = etat.source.fils().await?;
+    let fils = export::trier(fils.iter()
+        .filter(|fil| Status::comply_with(fil, &statut) && Pole::comply_with(fil, &pole)));
+    Ok(Json(Value::Array(fils.into_iter().map(export::fil_to_json).collect())))
+}
+
+/// `GET /fils/{id}`
+async fn afficher(State(etat): State<Arc<Etat>>, Path(id): Path<u64>) -> Result<Json<Value>, Erreur> {
+    etat.source.fils().await?.iter()
+        .find(|fil| fil.get_id() == id)
+        .map(|fil| Json(export::fil_to_json(fil)))
+        .ok_or(Erreur(StatusCode::NOT_FOUND, format!("Fil {id} inexistant.")))
+}
+
+fn reponse_flux(xml: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")], xml).into_response()
+}
+
+/// `GET /flux` : flux RSS des changements de statut de tous les pôles.
+async fn flux_global(State(etat): State<Arc<Etat>>) -> Result<Response, Erreur> {
+    Ok(reponse_flux(flux::generer(etat.source.fils().await?.iter(), None)))
+}
+
+/// `GET /flux/{pôle}`, le pôle étant désigné par son identifiant (`technique`, `retd`…).
+async fn flux_pole(State(etat): State<Arc<Etat>>, Path(pole): Path<String>) -> Result<Response, Erreur> {
+    let pole = flux::pole_depuis_identifiant(pole.trim_end_matches(".xml"))
+        .ok_or(Erreur(StatusCode::NOT_FOUND, format!("Pôle {pole} inexistant.")))?;
+    Ok(reponse_flux(flux::generer(etat.source.fils().await?.iter(), Some(&pole))))
+}
+
+fn reponse_calendrier(ics: String) -> Response {
+    ([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], ics).into_response()
+}
+
+/// `GET /calendrier` : calendrier iCalendar des fins de votes en cours, tous pôles confondus.
+async fn calendrier_global(State(etat): State<Arc<Etat>>) -> Result<Response, Erreur> {
+    Ok(reponse_calendrier(calendrier::generer(etat.source.fils().await?.iter(), None)))
+}
+
+/// `GET /calendrier/{pôle}`, le pôle étant désigné comme pour les flux.
+async fn calendrier_pole(State(etat): State<Arc<Etat>>, Path(pole): Path<String>) -> Result<Response, Erreur> {
+    let pole = flux::pole_depuis_identifiant(pole.trim_end_matches(".ics"))
+        .ok_or(Erreur(StatusCode::NOT_FOUND, format!("Pôle {pole} inexistant.")))?;
+    Ok(reponse_calendrier(calendrier::generer(etat.source.fils().await?.iter(), Some(&pole))))
+}
+
+pub fn routeur(source: Source, jeton: Option<String>) -> Router {
+    let etat = Arc::new(Etat { source, jeton });
+    Router::new()
+        .route("/fils", get(lister))
+        .route("/fils/:id", get(afficher))
+        .route("/flux", get(flux_global))
+        .route("/flux/:pole", get(flux_pole))
+        .route("/calendrier", get(calendrier_global))
+        .route("/calendrier/:pole", get(calendrier_pole))
+        .route_layer(middleware::from_fn_with_state(etat.clone(), authentifier))
+        .with_state(etat)
+}
+
+/// Lance le serveur HTTP ; ne rend la main qu’en cas d’erreur.
+pub async fn servir(source: Source, adresse: &str, jeton: Option<String>) -> Result<(), ErrType> {
+    if jeton.is_none() {
+        tracing::warn!(adresse, "Aucun jeton configuré : l’API HTTP est accessible sans authentification.");
+    }
+    let ecoute = TcpListener::bind(adresse).await?;
+    tracing::info!(adresse, source = ?source, "API HTTP lancée");
+    axum::serve(ecoute, routeur(source, jeton)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{self, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const JETON: &str = "secret";
+
+    async fn requete_brute(uri: &str, jeton: Option<&str>) -> (StatusCode, String) {
+        let source = Source::Fichier(stockage::tests::chemin_fixture("v2.yml"));
+        let mut requete = Request::builder().uri(uri);
+        if let Some(jeton) = jeton {
+            requete = requete.header(header::AUTHORIZATION, format!("Bearer {jeton}"));
+        }
+        let reponse = routeur(source, Some(JETON.to_string()))
+            .oneshot(requete.body(Body::empty()).unwrap()).await.unwrap();
+        let statut = reponse.status();
+        let corps = body::to_bytes(reponse.into_body(), usize::MAX).await.unwrap();
+        (statut, String::from_utf8(corps.to_vec()).unwrap())
+    }
+
+    async fn requete(uri: &str, jeton: Option<&str>) -> (StatusCode, Value) {
+        let (statut, corps) = requete_brute(uri, jeton).await;
+        (statut, serde_json::from_str(corps.as_str()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn refuse_sans_jeton() {
+        assert_eq!(requete("/fils", None).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(requete("/fils", Some("faux")).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn liste_les_fils() {
+        let (statut, fils) = requete("/fils", Some(JETON)).await;
+        assert_eq!(statut, StatusCode::OK);
+        assert_eq!(fils.as_array().unwrap().len(), 3);
+        assert!(fils[0]["id"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn filtre_par_pole() {
+        let (_, fils) = requete("/fils?pole=Technique&statut=Vote", Some(JETON)).await;
+        assert_eq!(fils.as_array().unwrap().len(), 1);
+        assert_eq!(fils[0]["id"], 1234567);
+        let (statut, _) = requete("/fils?statut=inexistant", Some(JETON)).await;
+        assert_eq!(statut, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn affiche_un_fil() {
+        let (statut, fil) = requete("/fils/1234567", Some(JETON)).await;
+        assert_eq!(statut, StatusCode::OK);
+        assert_eq!(fil["nom"], "Refonte du guide");
+        assert_eq!(requete("/fils/1", Some(JETON)).await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn flux_des_changements() {
+        let (statut, flux) = requete_brute(&format!("/flux?jeton={JETON}"), None).await;
+        assert_eq!(statut, StatusCode::OK);
+        let flux = rss::Channel::read_from(flux.as_bytes()).unwrap();
+        assert_eq!(flux.items().len(), 2);
+        /* Le plus récent en premier, sous le nom qu’avait alors le fil. */
+        assert_eq!(flux.items()[0].title(), Some("« Charte de modération » terminé"));
+
+        let (_, flux) = requete_brute("/flux/technique.xml", Some(JETON)).await;
+        let flux = rss::Channel::read_from(flux.as_bytes()).unwrap();
+        assert_eq!(flux.items().len(), 1);
+        assert_eq!(flux.items()[0].title(), Some("« Refonte du guide » passe au vote"));
+        assert_eq!(requete_brute("/flux/inexistant", Some(JETON)).await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn calendrier_des_votes() {
+        let (statut, ics) = requete_brute("/calendrier", Some(JETON)).await;
+        assert_eq!(statut, StatusCode::OK);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        /* Seul le fil au vote figure au calendrier, le fil terminé n’y est plus. */
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains("UID:vote-1234567@staffbot\r\n"));
+        assert!(ics.contains("DTSTART:20240721T221320Z\r\n"));
+        assert!(ics.lines().all(|ligne| ligne.len() <= 75));
+
+        let (_, ics) = requete_brute("/calendrier/legal.ics", Some(JETON)).await;
+        assert!(!ics.contains("BEGIN:VEVENT"));
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+
+use serde_json::json;
+use serenity::all::{CreateEmbed, CreateEmbedFooter, CreateMessage, Timestamp};
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::abonnements;
+use crate::annonces;
+use crate::config;
+use crate::discord;
+use crate::fil::Fil;
+use crate::flux;
+use crate::forum;
+
+/// Acteur attribué aux changements dont l’origine n’est pas connue.
+pub const INCONNU: &str = "Inconnu";
+
+/// État de la base tel que déjà publié, tenu à jour fil par fil, pour repérer les changements faits
+/// hors des points de modification du bot (commandes génériques de fondabots-lib) à la modification suivante.
+static REFERENCE: Mutex<Option<HashMap<u64, Fil>>> = Mutex::new(None);
+
+#[derive(Clone, Debug)]
+pub enum Action {
+    Ajout,
+    Suppression,
+    Renommage(String, String),
+    Pole(String, String),
+    Statut(String, String),
+    Echeance(String, String),
+    Responsables(String, String),
+    Note(String)
+}
+
+impl Action {
+    pub fn nom(&self) -> &'static str {
+        match self {
+            Action::Ajout => "ajout",
+            Action::Suppression => "suppression",
+            Action::Renommage(_, _) => "renommage",
+            Action::Pole(_, _) => "pole",
+            Action::Statut(_, _) => "statut",
+            Action::Echeance(_, _) => "echeance",
+            Action::Responsables(_, _) => "responsables",
+            Action::Note(_) => "note"
+        }
+    }
+
+    fn titre(&self) -> &'static str {
+        match self {
+            Action::Ajout => "Fil ajouté",
+            Action::Suppression => "Fil supprimé",
+            Action::Renommage(_, _) => "Fil renommé",
+            Action::Pole(_, _) => "Pôle modifié",
+            Action::Statut(_, _) => "Statut modifié",
+            Action::Echeance(_, _) => "Échéance modifiée",
+            Action::Responsables(_, _) => "Responsables modifiés",
+            Action::Note(_) => "Note ajoutée"
+        }
+    }
+
+    /// Valeurs avant et après le changement, s’il y a lieu.
+    pub fn valeurs(&self) -> (Option<&String>, Option<&String>) {
+        match self {
+            Action::Ajout | Action::Suppression => (None, None),
+            Action::Renommage(avant, apres) | Action::Pole(avant, apres) | Action::Statut(avant, apres)
+            | Action::Echeance(avant, apres) | Action::Responsables(avant, apres) => (Some(avant), Some(apres)),
+            Action::Note(texte) => (None, Some(texte))
+        }
+    }
+}
+
+/// Modification d’un fil de la base de données.
+#[derive(Clone, Debug)]
+pub struct Evenement {
+    pub date: Timestamp,
+    pub acteur: String,
+    pub fil: Fil,
+    pub action: Action
+}
+
+impl Evenement {
+    fn to_json(&self) -> String {
+        let (avant, apres) = self.action.valeurs();
+        json!({
+            "date": self.date.to_rfc3339(),
+            "acteur": self.acteur,
+            "fil": self.fil.get_id(),
+            "nom": self.fil.get_name(),
+            "lien": self.fil.get_lien(),
+            "action": self.action.nom(),
+            "avant": avant,
+            "apres": apres
+        }).to_string()
+    }
+
+    fn get_embed(&self) -> CreateEmbed {
+        let (avant, apres) = self.action.valeurs();
+        let mut embed = CreateEmbed::new()
+            .title(self.action.titre())
+            .description(format!("[{}]({})", self.fil.get_name(), self.fil.get_lien()))
+            .footer(CreateEmbedFooter::new(format!("{} · fil {}", self.acteur, self.fil.get_id())))
+            .timestamp(self.date)
+            .color(self.fil.pole.get_color());
+        if let Some(avant) = avant {
+            embed = embed.field("Avant", avant, true);
+        }
+        if let Some(apres) = apres {
+            embed = embed.field("Après", apres.chars().take(1024).collect::<String>(), true);
+        }
+        embed
+    }
+}
+
+/// Prend l’état initial de la base, auquel seront comparés les rattrapages.
+pub fn initialiser(database: &HashMap<u64, Fil>) {
+    *REFERENCE.lock().unwrap() = Some(database.clone());
+}
+
+/// Changements d’un fil entre deux états ; `None` s’il n’existe pas.
+fn comparer(avant: Option<&Fil>, apres: Option<&Fil>, acteur: &str, date: Timestamp) -> Vec<Evenement> {
+    let evenement = |fil: &Fil, action: Action| Evenement { date, acteur: acteur.to_string(), fil: fil.clone(), action };
+    let (ancien, fil) = match (avant, apres) {
+        (None, None) => return Vec::new(),
+        (None, Some(fil)) => return vec![evenement(fil, Action::Ajout)],
+        (Some(ancien), None) => return vec![evenement(ancien, Action::Suppression)],
+        (Some(ancien), Some(fil)) => (ancien, fil)
+    };
+    let mut evenements = Vec::new();
+    if ancien.get_name() != fil.get_name() {
+        evenements.push(evenement(fil, Action::Renommage(ancien.get_name().clone(), fil.get_name().clone())));
+    }
+    if ancien.pole != fil.pole {
+        evenements.push(evenement(fil, Action::Pole(ancien.pole.to_string(), fil.pole.to_string())));
+    }
+    if ancien.status != fil.status {
+        evenements.push(evenement(fil, Action::Statut(ancien.status.to_string(), fil.status.to_string())));
+    }
+    if ancien.echeance != fil.echeance {
+        let date = |echeance: Option<Timestamp>| echeance.and_then(|echeance| echeance.to_rfc3339()).unwrap_or("aucune".to_string());
+        evenements.push(evenement(fil, Action::Echeance(date(ancien.echeance), date(fil.echeance))));
+    }
+    if ancien.get_responsables() != fil.get_responsables() {
+        evenements.push(evenement(fil, Action::Responsables(ancien.get_responsables().join(", "), fil.get_responsables().join(", "))));
+    }
+    for note in fil.get_notes().iter().skip(ancien.get_notes().len()) {
+        evenements.push(evenement(fil, Action::Note(note.texte.clone())));
+    }
+    evenements
+}
+
+/// Modification de quelques fils par un acteur connu, dont l’état d’avant est retenu pour l’audit.
+///
+/// À commencer avant de toucher aux fils et à terminer une fois les changements faits.
+pub struct Modification {
+    acteur: String,
+    avant: HashMap<u64, Option<Fil>>
+}
+
+impl Modification {
+    /// Retient l’état des fils `ids`, existants ou non, avant leur modification par `acteur`.
+    pub fn commencer(database: &HashMap<u64, Fil>, ids: impl IntoIterator<Item = u64>, acteur: &str) -> Self {
+        Self {
+            acteur: acteur.to_string(),
+            avant: ids.into_iter().map(|id| (id, database.get(&id).cloned())).collect()
+        }
+    }
+
+    /// Modification qui n’a fait qu’ajouter les fils `ids`.
+    pub fn ajouts(ids: impl IntoIterator<Item = u64>, acteur: &str) -> Self {
+        Self { acteur: acteur.to_string(), avant: ids.into_iter().map(|id| (id, None)).collect() }
+    }
+
+    /// Publie les changements des fils retenus, attribués à l’acteur de la modification.
+    ///
+    /// Les changements faits depuis la modification précédente hors des points de modification du bot
+    /// (commandes génériques de fondabots-lib) sont publiés avant, attribués à [`INCONNU`].
+    pub fn terminer(self, database: &HashMap<u64, Fil>) -> Vec<Evenement> {
+        let date = Timestamp::now();
+        let evenements = {
+            let mut reference = REFERENCE.lock().unwrap();
+            let Some(reference) = reference.as_mut() else {
+                /* Sans état initial, on n’est pas dans le bot (export hors ligne…) : il n’y a rien à publier. */
+                return Vec::new();
+            };
+            /* État de chaque fil juste avant la modification. */
+            let avant = |id: &u64| match self.avant.get(id) {
+                Some(avant) => avant.as_ref(),
+                None => database.get(id)
+            };
+            let ids: BTreeSet<u64> = reference.keys().chain(database.keys()).chain(self.avant.keys()).copied().collect();
+            let mut evenements: Vec<Evenement> = ids.iter()
+                .filter(|id| reference.get(id) != avant(id))
+                .flat_map(|id| comparer(reference.get(id), avant(id), INCONNU, date))
+                .collect();
+            evenements.extend(self.avant.iter()
+                .flat_map(|(id, avant)| comparer(avant.as_ref(), database.get(id), self.acteur.as_str(), date)));
+            for id in ids {
+                match database.get(&id) {
+                    Some(fil) if reference.get(&id) != Some(fil) => reference.insert(id, fil.clone()),
+                    Some(_) => None,
+                    None => reference.remove(&id)
+                };
+            }
+            evenements
+        };
+        publier(&evenements);
+        evenements
+    }
+}
+
+fn publier(evenements: &[Evenement]) {
+    if evenements.is_empty() {
+        return;
+    }
+    flux::repercuter(evenements);
+    forum::repercuter(evenements);
+    abonnements::repercuter(evenements);
+    annonces::repercuter(evenements);
+    tokio::spawn(diffuser(evenements.to_vec()));
+}
+
+async fn diffuser(evenements: Vec<Evenement>) {
+    let config = &config::get().audit;
+    if let Some(journal) = &config.journal {
+        if let Err(e) = ecrire_journal(journal, &evenements) {
+            tracing::error!(erreur = %e, fichier = journal, "Erreur lors de l’écriture du journal d’audit");
+        }
+    }
+    if let Some(salon) = config.salon {
+        for paquet in evenements.chunks(10) {
+            let message = CreateMessage::new().embeds(paquet.iter().map(Evenement::get_embed).collect());
+            if let Err(e) = discord::envoyer(salon, message).await {
+                tracing::error!(erreur = %e, salon, "Erreur lors de l’envoi du journal d’audit");
+            }
+        }
+    }
+}
+
+fn ecrire_journal(chemin: &str, evenements: &[Evenement]) -> Result<(), ErrType> {
+    let mut fichier = OpenOptions::new().create(true).append(true).open(chemin)?;
+    for evenement in evenements {
+        writeln!(fichier, "{}", evenement.to_json())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fil::fields::{Pole, Status};
+    use crate::stockage;
+
+    use super::*;
+
+    fn resume(evenements: &[Evenement]) -> Vec<(u64, String, &'static str)> {
+        evenements.iter().map(|evenement| (evenement.fil.get_id(), evenement.acteur.clone(), evenement.action.nom())).collect()
+    }
+
+    #[tokio::test]
+    async fn attribue_chaque_changement() {
+        let mut database: HashMap<u64, Fil> = stockage::tests::fixture("v2.yml").into_iter()
+            .map(|fil| (fil.get_id(), fil)).collect();
+        initialiser(&database);
+
+        let modification = Modification::commencer(&database, [1234567], "alice");
+        database.get_mut(&1234567).unwrap().set_status(Status::Termine, Some("alice".to_string()));
+        let evenements = modification.terminer(&database);
+        assert_eq!(resume(&evenements), vec![(1234567, "alice".to_string(), "statut")]);
+        assert_eq!(evenements[0].action.valeurs(), (Some(&"Vote".to_string()), Some(&"Terminé".to_string())));
+
+        /* Changements faits hors du bot (commandes de fondabots-lib), constatés à la modification suivante,
+         * y compris sur le fil modifié. */
+        database.remove(&7654321);
+        database.get_mut(&1111111).unwrap().set_name("Règles du chat".to_string());
+        let modification = Modification::commencer(&database, [1111111], "bob");
+        database.get_mut(&1111111).unwrap().pole = Pole::Legal;
+        assert_eq!(resume(&modification.terminer(&database)), vec![
+            (1111111, INCONNU.to_string(), "renommage"),
+            (7654321, INCONNU.to_string(), "suppression"),
+            (1111111, "bob".to_string(), "pole")
+        ]);
+
+        database.insert(42, Fil::new("Nouveau".to_string(), "http://commandemento5.wikidot.com/forum/t-42/".to_string(),
+                                     Pole::Technique, Status::Discussion));
+        assert_eq!(resume(&Modification::ajouts([42], "Flux RSS").terminer(&database)), vec![(42, "Flux RSS".to_string(), "ajout")]);
+        assert!(Modification::commencer(&database, [42], "carole").terminer(&database).is_empty());
+    }
+}
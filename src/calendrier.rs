@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+
+use fondabots_lib::Object;
+
+use crate::export;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+
+fn date(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0).unwrap_or_default().format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Échappe un texte selon la RFC 5545.
+fn echapper(texte: &str) -> String {
+    texte.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+/// Ajoute une ligne de contenu, repliée à 75 octets comme l’exige la RFC 5545.
+fn ligne(ics: &mut String, contenu: String) {
+    let mut longueur = 0;
+    for caractere in contenu.chars() {
+        if longueur + caractere.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            longueur = 1;
+        }
+        ics.push(caractere);
+        longueur += caractere.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// Génère un calendrier iCalendar comportant un évènement par vote en cours ayant une échéance,
+/// pour tous les pôles ou un seul.
+pub fn generer<'a>(fils: impl Iterator<Item = &'a Fil>, pole: Option<&Pole>) -> String {
+    let maintenant = date(Utc::now().timestamp());
+    let nom = match pole {
+        Some(pole) => format!("Votes du staff — {pole}"),
+        None => "Votes du staff".to_string()
+    };
+    let mut ics = String::new();
+    ligne(&mut ics, "BEGIN:VCALENDAR".to_string());
+    ligne(&mut ics, "VERSION:2.0".to_string());
+    ligne(&mut ics, "PRODID:-//Fondation SCP//Staffbot//FR".to_string());
+    ligne(&mut ics, format!("X-WR-CALNAME:{}", echapper(nom.as_str())));
+    let votes = export::trier(fils.filter(|fil| fil.status == Status::Vote && fil.echeance.is_some()
+        && pole.is_none_or(|pole| fil.pole == *pole)));
+    for fil in votes {
+        let echeance = fil.echeance.unwrap().unix_timestamp();
+        ligne(&mut ics, "BEGIN:VEVENT".to_string());
+        ligne(&mut ics, format!("UID:vote-{}@staffbot", fil.get_id()));
+        ligne(&mut ics, format!("DTSTAMP:{maintenant}"));
+        ligne(&mut ics, format!("DTSTART:{}", date(echeance)));
+        ligne(&mut ics, format!("SUMMARY:{}", echapper(format!("Fin du vote : {}", fil.get_name()).as_str())));
+        ligne(&mut ics, format!("DESCRIPTION:{}", echapper(format!("Pôle {}.\n{}", fil.pole, fil.get_lien()).as_str())));
+        ligne(&mut ics, format!("URL:{}", fil.get_lien()));
+        ligne(&mut ics, format!("CATEGORIES:{}", echapper(fil.pole.to_string().as_str())));
+        ligne(&mut ics, "END:VEVENT".to_string());
+    }
+    ligne(&mut ics, "END:VCALENDAR".to_string());
+    ics
+}
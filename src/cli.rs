@@ -0,0 +1,113 @@
+use std::fs;
+use std::str::FromStr;
+
+use fondabots_lib::ErrType;
+use fondabots_lib::object::Field;
+
+use crate::api;
+use crate::config;
+use crate::export;
+use crate::export::Format;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+use crate::rapport;
+use crate::stockage;
+
+/// Options communes aux sous-commandes hors ligne : `--base`, `--sortie`, `--adresse`, `--statut` et `--pole`.
+#[derive(Default)]
+struct Options {
+    base: Option<String>,
+    sortie: Option<String>,
+    adresse: Option<String>,
+    statut: Option<Status>,
+    pole: Option<Pole>,
+    positionnels: Vec<String>
+}
+
+impl Options {
+    fn lire(args: &[String]) -> Result<Self, ErrType> {
+        let mut options = Options::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut valeur = || args.next().cloned()
+                .ok_or(ErrType::ObjectNotFound(format!("Valeur manquante pour l’option {arg}.")));
+            match arg.as_str() {
+                "--base" => options.base = Some(valeur()?),
+                "--sortie" => options.sortie = Some(valeur()?),
+                "--adresse" => options.adresse = Some(valeur()?),
+                "--statut" => options.statut = Some(Status::from_str(valeur()?.as_str())?),
+                "--pole" => options.pole = Some(Pole::from_str(valeur()?.as_str())?),
+                _ => options.positionnels.push(arg.clone())
+            }
+        }
+        Ok(options)
+    }
+
+    /// Fichier de base de données lu : celui où le bot enregistre la base, sauf `--base`.
+    fn base(&self) -> &str {
+        self.base.as_deref().unwrap_or(stockage::base())
+    }
+
+    fn ecrire(&self, contenu: String) -> Result<(), ErrType> {
+        match &self.sortie {
+            Some(chemin) => fs::write(chemin, contenu)?,
+            None => print!("{contenu}")
+        }
+        Ok(())
+    }
+}
+
+/// `staffbot exporter {format} [--statut S] [--pole P] [--base fichier] [--sortie fichier]`
+pub fn exporter(args: &[String]) -> Result<(), ErrType> {
+    let options = Options::lire(args)?;
+    let format = Format::from_str(options.positionnels.first()
+        .ok_or(ErrType::ObjectNotFound("Format d’export manquant (csv, json, markdown, yaml ou wikidot).".to_string()))?)?;
+    let fils = stockage::charger(options.base())?;
+    let fils = export::trier(fils.iter()
+        .filter(|fil| Status::comply_with(fil, &options.statut) && Pole::comply_with(fil, &options.pole)));
+    options.ecrire(format.exporter(&fils)?)
+}
+
+/// `staffbot rapport [--statut S] [--pole P] [--base fichier] [--sortie fichier.html]`
+pub fn rapport(args: &[String]) -> Result<(), ErrType> {
+    let options = Options::lire(args)?;
+    let fils = stockage::charger(options.base())?;
+    let fils: Vec<&Fil> = fils.iter()
+        .filter(|fil| Status::comply_with(fil, &options.statut) && Pole::comply_with(fil, &options.pole))
+        .collect();
+    options.ecrire(rapport::generer(&fils))
+}
+
+/// `staffbot migrer-sqlite [--base fichier] [--sortie fichier.db]`
+#[cfg(feature = "sqlite")]
+pub fn migrer_sqlite(args: &[String]) -> Result<(), ErrType> {
+    let options = Options::lire(args)?;
+    let sortie = options.sortie.clone().unwrap_or("./staffbot.db".to_string());
+    let base = options.base.as_deref().unwrap_or(stockage::BASE);
+    let nombre = stockage::sqlite::migrer(base, sortie.as_str())?;
+    println!("{nombre} fils migrés de {base} vers {sortie}.");
+    Ok(())
+}
+
+/// `staffbot servir [--base fichier] [--adresse hôte:port]`
+///
+/// Sert l’API HTTP à partir d’un fichier de base de données, sans se connecter à Discord.
+/// Le jeton est celui de la section `api` de la configuration, s’il y en a un.
+pub async fn servir(args: &[String]) -> Result<(), ErrType> {
+    let options = Options::lire(args)?;
+    let config = config::get().api.as_ref();
+    let adresse = options.adresse.clone()
+        .or(config.map(|api| api.adresse.clone()))
+        .unwrap_or(config::Api::ADRESSE_PAR_DEFAUT.to_string());
+    api::servir(api::Source::Fichier(options.base().to_string()), adresse.as_str(),
+                config.and_then(|api| api.jeton.clone())).await
+}
+
+pub fn usage() {
+    eprintln!("Utilisation :\n\
+    \tstaffbot {{token}} : lance le bot.\n\
+    \tstaffbot migrer-sqlite [--base fichier] [--sortie fichier.db] : crée une base SQLite à partir de la base YAML.\n\
+    \tstaffbot rapport [--statut S] [--pole P] [--base fichier] [--sortie fichier.html] : génère un rapport HTML imprimable.\n\
+    \tstaffbot servir [--base fichier] [--adresse hôte:port] : sert l’API HTTP à partir de la base, sans se connecter à Discord.\n\
+    \tstaffbot exporter {{csv|json|markdown|yaml|wikidot}} [--statut S] [--pole P] [--base fichier] [--sortie fichier] : exporte la base sans se connecter à Discord.");
+}
@@ -1,15 +1,109 @@
-use poise::{Command, Context, CreateReply};
-use serenity::all::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
 
-use fondabots_lib::ErrType;
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Europe::Paris;
+use poise::{ChoiceParameter, Command, Context, CreateReply};
+use serenity::all::{Attachment, ButtonStyle, ComponentInteractionCollector, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateInteractionResponse, Message, Timestamp, User};
+
+use fondabots_lib::{ErrType, Object};
 use fondabots_lib::generic_commands;
+use fondabots_lib::object::Field;
 use fondabots_lib::tools::alias;
 
 use crate::DataType;
+use crate::abonnements::{self, Abonne, Abonnement, Mode, Silence};
+use crate::audit;
+use crate::calendrier;
+use crate::config;
+use crate::entete;
+use crate::export;
+use crate::export::Format;
+use crate::flux;
+use crate::import;
+use crate::import::Resultat;
+use crate::liens::{self, Proposition};
+use crate::rapport;
+use crate::journalisation;
+use crate::ordre_du_jour::{self, OrdreDuJour, Presentation};
+use crate::passerelle;
+use crate::stats::{Periode, Statistiques};
+use crate::stockage;
+use crate::stockage::sauvegardes;
+use crate::fil::edition;
 use crate::fil::fields::Pole;
 use crate::fil::fields::Status;
+use crate::fil::releve::{self, Compte};
 use crate::fil::Fil;
 
+/// Construit une réponse contenant le résumé et le détail, ce dernier étant joint en fichier s’il est trop long.
+fn reponse_detaillee(resume: String, details: String, nom_fichier: &str) -> CreateReply {
+    if resume.len() + details.len() < 1900 {
+        CreateReply::default().content(format!("{resume}\n{details}"))
+    } else {
+        CreateReply::default().content(resume).attachment(CreateAttachment::bytes(details, nom_fichier))
+    }
+}
+
+/// Réserve une commande aux rôles autorisés à modifier les fils, comme les boutons des embeds.
+async fn editeur(ctx: Context<'_, DataType, ErrType>) -> Result<bool, ErrType> {
+    if edition::autorise(ctx.author_member().await.as_deref()) {
+        return Ok(true);
+    }
+    ctx.send(CreateReply::default().content("Vous n’avez pas la permission de modifier les fils.").ephemeral(true)).await?;
+    Ok(false)
+}
+
+/// Demande à l’auteur de la commande de confirmer par bouton. Renvoie vrai si la confirmation est donnée
+/// avant l’expiration du délai.
+async fn confirmer(ctx: Context<'_, DataType, ErrType>, reply: CreateReply) -> Result<bool, ErrType> {
+    /* Le préfixe « c » est ignoré par Fil::buttons, la réponse est gérée ici. */
+    let oui = format!("c-{}-o", ctx.id());
+    let non = format!("c-{}-n", ctx.id());
+    let handle = ctx.send(reply.components(vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(oui.clone()).style(ButtonStyle::Success).label("Confirmer"),
+        CreateButton::new(non.clone()).style(ButtonStyle::Danger).label("Annuler")
+    ])])).await?;
+    let reponse = ComponentInteractionCollector::new(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .custom_ids(vec![oui.clone(), non])
+        .timeout(Duration::from_secs(120))
+        .await;
+    let confirme = reponse.as_ref().is_some_and(|interaction| interaction.data.custom_id == oui);
+    if let Some(interaction) = reponse {
+        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+    }
+    handle.edit(ctx, CreateReply::default().components(vec![])).await?;
+    Ok(confirme)
+}
+
+/// Trouve l’unique fil correspondant au critère : son identifiant, son lien ou une partie de son nom.
+fn trouver_fil(database: &HashMap<u64, Fil>, critere: &str) -> Result<u64, String> {
+    if let Some(id) = critere.parse().ok().or(Fil::find_id(&critere.to_string())) {
+        return if database.contains_key(&id) {
+            Ok(id)
+        } else {
+            Err(format!("Aucun fil d’identifiant {id}."))
+        };
+    }
+    let critere = critere.to_lowercase();
+    let ids: Vec<u64> = database.values()
+        .filter(|fil| fil.get_name().to_lowercase().contains(critere.as_str()))
+        .map(|fil| fil.get_id()).collect();
+    match ids.as_slice() {
+        [id] => Ok(*id),
+        [] => Err(format!("Aucun fil ne correspond au critère « {critere} ».")),
+        _ => Err(format!("{} fils correspondent au critère « {critere} », précisez-le.", ids.len()))
+    }
+}
+
+/// Commence la modification du fil que vise une commande générique de fondabots-lib, s’il est trouvé.
+async fn commencer_generique(ctx: Context<'_, DataType, ErrType>, critere: &str) -> audit::Modification {
+    let bot = ctx.data().lock().await;
+    audit::Modification::commencer(&bot.database, trouver_fil(&bot.database, critere).ok(), ctx.author().name.as_str())
+}
+
 /// Ajoute manuellement un fil à la base de données.
 #[poise::command(slash_command)]
 pub async fn ajouter(
@@ -19,9 +113,12 @@ pub async fn ajouter(
     #[description = "Statut du fil"] statut: Status,
     #[description = "Lien forum du fil"] url: String
 ) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
     let bot = &mut ctx.data().lock().await;
     if let Some(id) = Fil::find_id(&url) {
+        let modification = audit::Modification::commencer(&bot.database, [id], ctx.author().name.as_str());
         bot.database.insert(id, Fil::new(nom.clone(), url, pole, statut));
+        modification.terminer(&bot.database);
         ctx.say(format!("Fil « {nom} » ajouté !")).await?;
     } else {
         ctx.say("URL malformée, impossible de déterminer l’identifiant du fil.").await?;
@@ -34,7 +131,11 @@ pub async fn ajouter(
 pub async fn pole(ctx: Context<'_, DataType, ErrType>,
                   #[description = "Critère d’identification du fil"] critere: String,
                   #[description = "Nouveau pôle du fil"] pole: Pole) -> Result<(), ErrType> {
-    generic_commands::change_field(ctx, critere, pole).await
+    passerelle::partager(ctx.serenity_context());
+    let modification = commencer_generique(ctx, critere.as_str()).await;
+    generic_commands::change_field(ctx, critere, pole).await?;
+    modification.terminer(&ctx.data().lock().await.database);
+    Ok(())
 }
 
 /// Change le statut d’un fil
@@ -42,7 +143,273 @@ pub async fn pole(ctx: Context<'_, DataType, ErrType>,
 pub async fn statut(ctx: Context<'_, DataType, ErrType>,
                     #[description = "Critère d’identification du fil"] critere: String,
                     #[description = "Nouveau statut du fil"] statut: Status) -> Result<(), ErrType> {
-    generic_commands::change_field(ctx, critere, statut).await
+    passerelle::partager(ctx.serenity_context());
+    let modification = commencer_generique(ctx, critere.as_str()).await;
+    generic_commands::change_field(ctx, critere, statut).await?;
+    modification.terminer(&ctx.data().lock().await.database);
+    Ok(())
+}
+
+/// Fils visés par `/statut-masse` : du pôle et du statut demandés, sans mise à jour depuis la date `limite`,
+/// et qui n’ont pas déjà le nouveau statut.
+fn selection_masse(nouveau: Status, pole: Option<Pole>, statut: Option<Status>, limite: Option<i64>) -> impl Fn(&Fil) -> bool {
+    move |fil: &Fil| Status::comply_with(fil, &statut) && Pole::comply_with(fil, &pole)
+        && fil.status != nouveau && limite.is_none_or(|limite| fil.get_date().timestamp() < limite)
+}
+
+/// Change en une fois le statut de tous les fils correspondant aux filtres.
+#[poise::command(slash_command, rename = "statut-masse", check = "editeur")]
+pub async fn statut_masse(ctx: Context<'_, DataType, ErrType>,
+                          #[description = "Nouveau statut des fils"] nouveau: Status,
+                          #[description = "Pôle des fils à modifier"] pole: Option<Pole>,
+                          #[description = "Statut actuel des fils à modifier"] statut: Option<Status>,
+                          #[description = "Ne modifier que les fils sans mise à jour depuis ce nombre de jours"] jours: Option<u32>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let limite = jours.map(|jours| Timestamp::now().timestamp() - i64::from(jours) * 86400);
+    let selection = selection_masse(nouveau.clone(), pole, statut, limite);
+
+    let (ids, details): (Vec<u64>, String) = {
+        let bot = ctx.data().lock().await;
+        let fils = export::trier(bot.database.values().filter(|fil| selection(fil)));
+        (fils.iter().map(|fil| fil.get_id()).collect(), fils.iter()
+            .map(|fil| format!("- [{}](<{}>) ({}) : {} → {nouveau}", fil.get_name(), fil.get_lien(), fil.pole, fil.status))
+            .collect::<Vec<String>>().join("\n"))
+    };
+    if ids.is_empty() {
+        ctx.say("Aucun fil ne correspond à ces critères.").await?;
+        return Ok(());
+    }
+    let resume = format!("{} fil(s) vont passer au statut « {nouveau} » :", ids.len());
+    if !confirmer(ctx, reponse_detaillee(resume, details, "statut-masse.txt")).await? {
+        ctx.say("Changement de statut annulé.").await?;
+        return Ok(());
+    }
+
+    let bot = &mut ctx.data().lock().await;
+    /* On ne garde que les fils qui correspondent toujours aux critères après la confirmation. */
+    let ids: Vec<u64> = ids.into_iter()
+        .filter(|id| bot.database.get(id).is_some_and(&selection)).collect();
+    let modification = audit::Modification::commencer(&bot.database, ids.clone(), ctx.author().name.as_str());
+    bot.archive(ids.clone());
+    for id in &ids {
+        let fil = bot.database.get_mut(id).unwrap();
+        fil.set_status(nouveau.clone(), Some(ctx.author().name.clone()));
+        fil.set_modified(true);
+    }
+    modification.terminer(&bot.database);
+    entete::actualiser(ctx.serenity_context(), bot).await?;
+    stockage::enregistrer(bot)?;
+    ctx.say(format!("{} fil(s) passé(s) au statut « {nouveau} ». `/annuler` annule l’ensemble du changement.", ids.len())).await?;
+    Ok(())
+}
+
+/// Ajoute une note à un fil.
+#[poise::command(slash_command)]
+pub async fn noter(ctx: Context<'_, DataType, ErrType>,
+                   #[description = "Critère d’identification du fil"] critere: String,
+                   #[description = "Texte de la note"] texte: String) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let bot = &mut ctx.data().lock().await;
+    match trouver_fil(&bot.database, critere.as_str()) {
+        Ok(id) => {
+            let modification = audit::Modification::commencer(&bot.database, [id], ctx.author().name.as_str());
+            bot.archive(vec![id]);
+            let fil = bot.database.get_mut(&id).unwrap();
+            fil.ajouter_note(ctx.author().name.clone(), texte);
+            fil.set_modified(true);
+            let nom = fil.get_name().clone();
+            modification.terminer(&bot.database);
+            stockage::enregistrer(bot)?;
+            ctx.say(format!("Note ajoutée au fil « {nom} ».")).await?;
+        }
+        Err(e) => { ctx.say(e).await?; }
+    }
+    Ok(())
+}
+
+/// Lit une date au format `JJ/MM/AAAA [HH:MM]`, à l’heure de Paris. Sans heure, la fin de la journée est retenue.
+fn lire_date(texte: &str) -> Result<Timestamp, String> {
+    let texte = texte.trim();
+    let date = NaiveDateTime::parse_from_str(texte, "%d/%m/%Y %H:%M")
+        .or_else(|_| NaiveDate::parse_from_str(texte, "%d/%m/%Y").map(|date| date.and_hms_opt(23, 59, 0).unwrap()))
+        .map_err(|_| format!("Date « {texte} » invalide : utilisez le format JJ/MM/AAAA ou JJ/MM/AAAA HH:MM."))?;
+    let date = Paris.from_local_datetime(&date).earliest()
+        .ok_or(format!("L’heure de « {texte} » n’existe pas à Paris (changement d’heure)."))?;
+    Timestamp::from_unix_timestamp(date.timestamp()).map_err(|e| e.to_string())
+}
+
+/// Fixe ou retire la date de fin du vote d’un fil.
+#[poise::command(slash_command)]
+pub async fn echeance(ctx: Context<'_, DataType, ErrType>,
+                      #[description = "Critère d’identification du fil"] critere: String,
+                      #[description = "Fin du vote (JJ/MM/AAAA [HH:MM]) ; absente, retire l’échéance"] date: Option<String>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let echeance = match date.as_deref().map(lire_date).transpose() {
+        Ok(echeance) => echeance,
+        Err(e) => {
+            ctx.say(e).await?;
+            return Ok(());
+        }
+    };
+    let bot = &mut ctx.data().lock().await;
+    match trouver_fil(&bot.database, critere.as_str()) {
+        Ok(id) => {
+            let modification = audit::Modification::commencer(&bot.database, [id], ctx.author().name.as_str());
+            bot.archive(vec![id]);
+            let fil = bot.database.get_mut(&id).unwrap();
+            fil.echeance = echeance;
+            fil.set_modified(true);
+            let message = match echeance {
+                Some(echeance) => format!("Fin du vote du fil « {} » fixée au <t:{}:f>.", fil.get_name(), echeance.unix_timestamp()),
+                None => format!("Échéance du fil « {} » retirée.", fil.get_name())
+            };
+            modification.terminer(&bot.database);
+            entete::actualiser(ctx.serenity_context(), bot).await?;
+            stockage::enregistrer(bot)?;
+            ctx.say(message).await?;
+        }
+        Err(e) => { ctx.say(e).await?; }
+    }
+    Ok(())
+}
+
+/// Ajoute ou retire un responsable d’un fil.
+#[poise::command(slash_command)]
+pub async fn assigner(ctx: Context<'_, DataType, ErrType>,
+                      #[description = "Critère d’identification du fil"] critere: String,
+                      #[description = "Membre du staff"] membre: User,
+                      #[description = "Retirer le membre des responsables au lieu de l’ajouter"] retirer: Option<bool>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let bot = &mut ctx.data().lock().await;
+    match trouver_fil(&bot.database, critere.as_str()) {
+        Ok(id) => {
+            let modification = audit::Modification::commencer(&bot.database, [id], ctx.author().name.as_str());
+            bot.archive(vec![id]);
+            let fil = bot.database.get_mut(&id).unwrap();
+            let nom = fil.get_name().clone();
+            let message = if retirer.unwrap_or(false) {
+                match fil.desassigner(membre.name.as_str()) {
+                    true => format!("{} n’est plus responsable du fil « {nom} ».", membre.name),
+                    false => format!("{} n’était pas responsable du fil « {nom} ».", membre.name)
+                }
+            } else {
+                match fil.assigner(membre.name.clone()) {
+                    true => format!("{} est désormais responsable du fil « {nom} ».", membre.name),
+                    false => format!("{} est déjà responsable du fil « {nom} ».", membre.name)
+                }
+            };
+            fil.set_modified(true);
+            modification.terminer(&bot.database);
+            entete::actualiser(ctx.serenity_context(), bot).await?;
+            stockage::enregistrer(bot)?;
+            ctx.say(message).await?;
+        }
+        Err(e) => { ctx.say(e).await?; }
+    }
+    Ok(())
+}
+
+/// Saisie des informations d’un fil à enregistrer depuis un lien.
+#[derive(Debug, poise::Modal)]
+#[name = "Enregistrer un fil"]
+struct Enregistrement {
+    #[name = "Nom du fil"]
+    #[max_length = 200]
+    nom: String,
+    #[name = "Pôle"]
+    pole: String,
+    #[name = "Statut"]
+    statut: String
+}
+
+fn boutons_enregistrement(prefixe: &str, propositions: &[(u64, String, Proposition)], enregistres: &[bool]) -> Vec<CreateActionRow> {
+    let boutons: Vec<CreateButton> = propositions.iter().zip(enregistres).enumerate().map(|(i, ((_, _, proposition), enregistre))| {
+        let nom: String = proposition.nom.chars().take(60).collect();
+        CreateButton::new(format!("{prefixe}{i}"))
+            .style(if *enregistre { ButtonStyle::Secondary } else { ButtonStyle::Primary })
+            .label(if nom.is_empty() { format!("Enregistrer le lien {}", i + 1) } else { format!("Enregistrer « {nom} »") })
+            .disabled(*enregistre)
+    }).collect();
+    boutons.chunks(5).map(|ligne| CreateActionRow::Buttons(ligne.to_vec())).collect()
+}
+
+/// Enregistre les fils du forum dont le message contient le lien, ou affiche ceux déjà suivis.
+#[poise::command(context_menu_command = "Enregistrer ce lien")]
+pub async fn enregistrer_lien(ctx: Context<'_, DataType, ErrType>, message: Message) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    ctx.defer_ephemeral().await?;
+    let liens = liens::extraire(message.content.as_str());
+    if liens.is_empty() {
+        ctx.say("Aucun lien de fil du forum dans ce message.").await?;
+        return Ok(());
+    }
+    let (connus, inconnus): (Vec<_>, Vec<_>) = {
+        let bot = ctx.data().lock().await;
+        liens.into_iter().partition(|(id, _)| bot.database.contains_key(id))
+    };
+    let embeds: Vec<CreateEmbed> = {
+        let bot = ctx.data().lock().await;
+        connus.iter().filter_map(|(id, _)| bot.database.get(id)).take(10).map(Fil::get_embed).collect()
+    };
+    let mut propositions = Vec::new();
+    for (id, lien) in inconnus.into_iter().take(25) {
+        let proposition = liens::deviner(lien.as_str()).await;
+        propositions.push((id, lien, proposition));
+    }
+    let mut enregistres = vec![false; propositions.len()];
+    let prefixe = format!("c-{}-", ctx.id());
+    let mut reply = CreateReply::default()
+        .content(format!("{} fil(s) déjà suivi(s), {} à enregistrer.", connus.len(), propositions.len()))
+        .components(boutons_enregistrement(prefixe.as_str(), &propositions, &enregistres))
+        .ephemeral(true);
+    for embed in embeds {
+        reply = reply.embed(embed);
+    }
+    let handle = ctx.send(reply).await?;
+
+    while enregistres.contains(&false) {
+        let filtre = prefixe.clone();
+        let Some(interaction) = ComponentInteractionCollector::new(ctx.serenity_context())
+            .author_id(ctx.author().id)
+            .filter(move |interaction| interaction.data.custom_id.starts_with(filtre.as_str()))
+            .timeout(Duration::from_secs(600))
+            .await else {
+            break;
+        };
+        let Some(i) = interaction.data.custom_id[prefixe.len()..].parse::<usize>().ok().filter(|i| *i < propositions.len()) else {
+            continue;
+        };
+        let (id, lien, proposition) = &propositions[i];
+        let defaut = Enregistrement { nom: proposition.nom.clone(), pole: proposition.pole.to_string(), statut: proposition.statut.to_string() };
+        let Some(saisie) = poise::execute_modal_on_component_interaction(ctx, interaction, Some(defaut), Some(Duration::from_secs(600))).await? else {
+            continue;
+        };
+        let pole = Pole::from_str(saisie.pole.trim()).ok().or(flux::pole_depuis_identifiant(saisie.pole.trim()));
+        let message = match (pole, Status::from_str(saisie.statut.trim())) {
+            (None, _) => format!("Pôle « {} » inexistant.", saisie.pole),
+            (_, Err(_)) => format!("Statut « {} » inexistant.", saisie.statut),
+            (Some(pole), Ok(statut)) => {
+                let bot = &mut ctx.data().lock().await;
+                enregistres[i] = true;
+                if bot.database.contains_key(id) {
+                    "Ce fil a été enregistré entre-temps.".to_string()
+                } else {
+                    let modification = audit::Modification::commencer(&bot.database, [*id], ctx.author().name.as_str());
+                    let mut fil = Fil::new(saisie.nom.clone(), lien.clone(), pole, statut);
+                    fil.reponses = proposition.reponses;
+                    bot.database.insert(*id, fil);
+                    modification.terminer(&bot.database);
+                    entete::actualiser(ctx.serenity_context(), bot).await?;
+                    stockage::enregistrer(bot)?;
+                    format!("Fil « {} » ajouté !", saisie.nom)
+                }
+            }
+        };
+        handle.edit(ctx, CreateReply::default().components(boutons_enregistrement(prefixe.as_str(), &propositions, &enregistres))).await?;
+        ctx.send(CreateReply::default().content(message).ephemeral(true)).await?;
+    }
+    handle.edit(ctx, CreateReply::default().components(vec![])).await?;
+    Ok(())
 }
 
 /// Liste les fils correspondant aux statut et poles demandés.
@@ -50,12 +417,428 @@ pub async fn statut(ctx: Context<'_, DataType, ErrType>,
 pub async fn lister(ctx: Context<'_, DataType, ErrType>,
                     #[description = "Statut recherché"] statut: Option<Status>,
                     #[description = "Pôle recherché"] pole: Option<Pole>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
     generic_commands::lister_two(ctx, statut, pole).await
 }
 
+/// Exporte les fils correspondant aux statut et pôle demandés dans un fichier.
+#[poise::command(slash_command)]
+pub async fn exporter(ctx: Context<'_, DataType, ErrType>,
+                      #[description = "Format du fichier"] format: Format,
+                      #[description = "Statut recherché"] statut: Option<Status>,
+                      #[description = "Pôle recherché"] pole: Option<Pole>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let bot = ctx.data().lock().await;
+    let fils = export::trier(bot.database.values()
+        .filter(|fil| Status::comply_with(fil, &statut) && Pole::comply_with(fil, &pole)));
+    let contenu = format.exporter(&fils)?;
+    let resume = format!("{} fil(s) exporté(s) au format {format}.", fils.len());
+    /* Le texte wikidot est destiné à être copié : tant qu’il tient dans le message, il y est mis directement. */
+    let reply = if format == Format::Wikidot && resume.len() + contenu.len() < 1900 {
+        CreateReply::default().content(format!("{resume}\n```\n{contenu}```"))
+    } else {
+        CreateReply::default().content(resume).attachment(CreateAttachment::bytes(contenu, format!("fils.{}", format.extension())))
+    };
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+/// Importe en masse des fils depuis un fichier CSV ou YAML (nom, url, pole, statut).
+#[poise::command(slash_command, check = "editeur")]
+pub async fn importer(ctx: Context<'_, DataType, ErrType>,
+                      #[description = "Fichier CSV ou YAML des fils à importer"] fichier: Attachment) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let contenu = String::from_utf8(fichier.download().await?).map_err(import::erreur)?;
+    let lignes = import::lire(fichier.filename.as_str(), contenu.as_str())?;
+    let resultats = import::analyser(&ctx.data().lock().await.database, &lignes);
+    let details = resultats.iter()
+        .map(|(numero, resultat)| format!("Ligne {numero} : {resultat}"))
+        .collect::<Vec<String>>().join("\n");
+    let nb_modifications = resultats.iter()
+        .filter(|(_, resultat)| matches!(resultat, Resultat::Ajout(_) | Resultat::MiseAJour(_, _))).count();
+    let nb_erreurs = resultats.iter().filter(|(_, resultat)| matches!(resultat, Resultat::Erreur(_))).count();
+    let resume = format!("Simulation de l’import de {} ligne(s) : {nb_modifications} modification(s), {nb_erreurs} erreur(s).", lignes.len());
+
+    if nb_modifications == 0 {
+        ctx.send(reponse_detaillee(format!("{resume} Rien à importer."), details, "import.txt")).await?;
+        return Ok(());
+    }
+    if !confirmer(ctx, reponse_detaillee(format!("{resume} Les lignes en erreur seront ignorées."), details, "import.txt")).await? {
+        ctx.say("Import annulé.").await?;
+        return Ok(());
+    }
+
+    let bot = &mut ctx.data().lock().await;
+    /* La base a pu changer pendant la confirmation : on refait l’analyse avant d’appliquer. */
+    let fils: Vec<Fil> = import::analyser(&bot.database, &lignes).into_iter()
+        .filter_map(|(_, resultat)| match resultat {
+            Resultat::Ajout(fil) | Resultat::MiseAJour(fil, _) => Some(fil),
+            _ => None
+        }).collect();
+    let ids: Vec<u64> = fils.iter().map(|fil| fil.get_id()).collect();
+    let modification = audit::Modification::commencer(&bot.database, ids.clone(), ctx.author().name.as_str());
+    bot.archive(ids);
+    let nb_fils = fils.len();
+    for fil in fils {
+        bot.database.insert(fil.get_id(), fil);
+    }
+    modification.terminer(&bot.database);
+    entete::actualiser(ctx.serenity_context(), bot).await?;
+    stockage::enregistrer(bot)?;
+    ctx.say(format!("Import terminé : {nb_fils} fil(s) ajouté(s) ou mis à jour. `/annuler` annule l’import entier.")).await?;
+    Ok(())
+}
+
+/// Affiche des statistiques sur les fils et leurs changements de statut.
+#[poise::command(slash_command)]
+pub async fn stats(ctx: Context<'_, DataType, ErrType>,
+                   #[description = "Pôle (tous par défaut)"] pole: Option<Pole>,
+                   #[description = "Période (30 derniers jours par défaut)"] periode: Option<Periode>,
+                   #[description = "Joindre un graphique des nouveaux fils par semaine"] graphique: Option<bool>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let stats = Statistiques::calculer(ctx.data().lock().await.database.values(), pole, periode.unwrap_or(Periode::Mois));
+    let mut reply = CreateReply::default().embed(stats.get_embed());
+    if graphique.unwrap_or(false) {
+        #[cfg(feature = "graphiques")]
+        match crate::stats::graphique::dessiner(&stats) {
+            Ok(png) => reply = reply.attachment(CreateAttachment::bytes(png, "stats.png")),
+            Err(e) => {
+                tracing::error!(erreur = %e, "Erreur lors du dessin du graphique des statistiques");
+                reply = reply.content("Le graphique n’a pas pu être généré.");
+            }
+        }
+        #[cfg(not(feature = "graphiques"))]
+        {
+            reply = reply.content("Les graphiques ne sont pas disponibles : le bot a été compilé sans la fonctionnalité « graphiques ».");
+        }
+    }
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+/// Prépare l’ordre du jour de la réunion du staff (votes, développements, discussions en suspens).
+#[poise::command(slash_command, rename = "ordre-du-jour")]
+pub async fn ordre_du_jour(ctx: Context<'_, DataType, ErrType>,
+                           #[description = "Pôles concernés, séparés par des virgules (tous par défaut)"] poles: Option<String>,
+                           #[description = "Message Discord ou texte wikidot à coller sur le forum"] format: Option<Presentation>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let poles = match ordre_du_jour::lire_poles(poles.as_deref().unwrap_or_default()) {
+        Ok(poles) => poles,
+        Err(e) => {
+            ctx.say(e).await?;
+            return Ok(());
+        }
+    };
+    let presentation = format.unwrap_or(Presentation::Discord);
+    let reply = {
+        let bot = ctx.data().lock().await;
+        let ordre_du_jour = OrdreDuJour::preparer(bot.database.values(), &poles, Utc::now());
+        let texte = ordre_du_jour.rendre(presentation);
+        if texte.len() <= ordre_du_jour::LONGUEUR_MAX {
+            CreateReply::default().content(texte)
+        } else {
+            CreateReply::default().content("Ordre du jour trop long pour un message, le voici en pièce jointe.")
+                .attachment(CreateAttachment::bytes(ordre_du_jour.fichier(presentation), ordre_du_jour.nom_fichier(presentation)))
+        }
+    };
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+/// Envoie un rapport HTML imprimable des fils, regroupés par pôle.
+#[poise::command(slash_command)]
+pub async fn rapport(ctx: Context<'_, DataType, ErrType>,
+                     #[description = "Statut recherché"] statut: Option<Status>,
+                     #[description = "Pôle recherché"] pole: Option<Pole>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let bot = ctx.data().lock().await;
+    let fils: Vec<&Fil> = bot.database.values()
+        .filter(|fil| Status::comply_with(fil, &statut) && Pole::comply_with(fil, &pole))
+        .collect();
+    ctx.send(CreateReply::default()
+        .content(format!("Rapport de {} fil(s).", fils.len()))
+        .attachment(CreateAttachment::bytes(rapport::generer(&fils), "rapport.html"))
+    ).await?;
+    Ok(())
+}
+
+/// Envoie le calendrier des fins de votes en cours, à importer dans un agenda.
+#[poise::command(slash_command)]
+pub async fn calendrier(ctx: Context<'_, DataType, ErrType>,
+                        #[description = "Pôle des votes (tous par défaut)"] pole: Option<Pole>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let ics = calendrier::generer(ctx.data().lock().await.database.values(), pole.as_ref());
+    let nom_fichier = match &pole {
+        Some(pole) => format!("votes-{}.ics", flux::identifiant(pole)),
+        None => "votes.ics".to_string()
+    };
+    ctx.send(CreateReply::default()
+        .content("Calendrier des votes en cours. Pour un calendrier mis à jour automatiquement, demandez l’adresse de l’API aux administrateurs.")
+        .attachment(CreateAttachment::bytes(ics, nom_fichier))).await?;
+    Ok(())
+}
+
+/// Fil ou pôle désigné par les options d’une commande d’abonnement, avec sa description.
+async fn lire_abonnement(ctx: Context<'_, DataType, ErrType>, critere: Option<String>, pole: Option<Pole>) -> Result<(Abonnement, String), String> {
+    match (critere, pole) {
+        (Some(critere), None) => {
+            let bot = ctx.data().lock().await;
+            let id = trouver_fil(&bot.database, critere.as_str())?;
+            Ok((Abonnement::Fil(id), format!("le fil « {} »", bot.database[&id].get_name())))
+        }
+        (None, Some(pole)) => Ok((Abonnement::Pole(pole.clone()), format!("le pôle {pole}"))),
+        _ => Err("Précisez soit un fil, soit un pôle.".to_string())
+    }
+}
+
+/// Suit les changements de statut, notes et fins de vote d’un fil ou des fils d’un pôle.
+#[poise::command(slash_command)]
+pub async fn abonner(ctx: Context<'_, DataType, ErrType>,
+                     #[description = "Critère d’identification du fil"] critere: Option<String>,
+                     #[description = "Pôle dont suivre tous les fils"] pole: Option<Pole>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let message = match lire_abonnement(ctx, critere, pole).await {
+        Ok((abonnement, description)) => {
+            let (id, nom) = (ctx.author().id.get(), ctx.author().name.clone());
+            let nouveau = abonnements::acceder(|abonnements| {
+                let abonne = abonnements.abonnes.entry(id).or_insert_with(|| Abonne::new(nom.clone()));
+                abonne.nom = nom;
+                let nouveau = !abonne.abonnements.contains(&abonnement);
+                if nouveau {
+                    abonne.abonnements.push(abonnement);
+                }
+                nouveau
+            })?;
+            match nouveau {
+                true => format!("Vous suivez désormais {description}. `/notifications` règle la façon d’être notifié."),
+                false => format!("Vous suiviez déjà {description}.")
+            }
+        }
+        Err(e) => e
+    };
+    ctx.send(CreateReply::default().content(message).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Arrête de suivre un fil ou un pôle, ou tout si aucun n’est précisé.
+#[poise::command(slash_command)]
+pub async fn desabonner(ctx: Context<'_, DataType, ErrType>,
+                        #[description = "Critère d’identification du fil"] critere: Option<String>,
+                        #[description = "Pôle"] pole: Option<Pole>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let id = ctx.author().id.get();
+    let message = if critere.is_none() && pole.is_none() {
+        abonnements::acceder(|abonnements| abonnements.abonnes.get_mut(&id)
+            .map(|abonne| abonne.abonnements.clear()))?;
+        "Vous ne suivez plus aucun fil ni pôle.".to_string()
+    } else {
+        match lire_abonnement(ctx, critere, pole).await {
+            Ok((abonnement, description)) => {
+                let retire = abonnements::acceder(|abonnements| abonnements.abonnes.get_mut(&id).is_some_and(|abonne| {
+                    let avant = abonne.abonnements.len();
+                    abonne.abonnements.retain(|autre| *autre != abonnement);
+                    abonne.abonnements.len() != avant
+                }))?;
+                match retire {
+                    true => format!("Vous ne suivez plus {description}."),
+                    false => format!("Vous ne suiviez pas {description}.")
+                }
+            }
+            Err(e) => e
+        }
+    };
+    ctx.send(CreateReply::default().content(message).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Règle la réception des notifications et affiche vos abonnements.
+#[poise::command(slash_command)]
+pub async fn notifications(ctx: Context<'_, DataType, ErrType>,
+                           #[description = "Message privé ou mention dans le salon des notifications"] mode: Option<Mode>,
+                           #[description = "Heures de silence (HH:MM-HH:MM), ou « aucune »"] silence: Option<String>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let silence = match silence.as_deref().map(str::trim) {
+        None => None,
+        Some("aucune") => Some(None),
+        Some(texte) => match Silence::lire(texte) {
+            Ok(silence) => Some(Some(silence)),
+            Err(e) => {
+                ctx.send(CreateReply::default().content(e).ephemeral(true)).await?;
+                return Ok(());
+            }
+        }
+    };
+    let (id, nom) = (ctx.author().id.get(), ctx.author().name.clone());
+    let abonne = abonnements::acceder(|abonnements| {
+        let abonne = abonnements.abonnes.entry(id).or_insert_with(|| Abonne::new(nom.clone()));
+        abonne.nom = nom;
+        if let Some(mode) = mode {
+            abonne.mode = mode;
+        }
+        if let Some(silence) = silence {
+            abonne.silence = silence;
+        }
+        abonne.clone()
+    })?;
+    let suivis = {
+        let bot = ctx.data().lock().await;
+        abonne.abonnements.iter().map(|abonnement| match abonnement {
+            Abonnement::Fil(id) => match bot.database.get(id) {
+                Some(fil) => format!("- [{}](<{}>)", fil.get_name(), fil.get_lien()),
+                None => format!("- Fil {id} (supprimé)")
+            },
+            Abonnement::Pole(pole) => format!("- Pôle {pole}")
+        }).collect::<Vec<String>>().join("\n")
+    };
+    let mode = match (abonne.mode, crate::config::get().notifications.salon) {
+        (Mode::Salon, None) => "Message privé (aucun salon des notifications n’est configuré)".to_string(),
+        (mode, _) => mode.name().to_string()
+    };
+    ctx.send(CreateReply::default().embed(CreateEmbed::new()
+        .title("Vos notifications")
+        .field("Réception", mode, true)
+        .field("Heures de silence", abonne.silence.map(|silence| silence.to_string()).unwrap_or("aucune".to_string()), true)
+        .field("Abonnements", if suivis.is_empty() { "Aucun. Utilisez `/abonner`.".to_string() } else { suivis }, false)
+    ).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Liste les sauvegardes de la base de données.
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn sauvegardes(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let sauvegardes = sauvegardes::lister()?;
+    if sauvegardes.is_empty() {
+        ctx.say("Aucune sauvegarde disponible.").await?;
+        return Ok(());
+    }
+    let liste = sauvegardes.iter().take(20)
+        .map(|sauvegarde| format!("`{}` : <t:{}:R>, {} ko", sauvegarde.nom, sauvegarde.date.timestamp(), sauvegarde.taille.div_ceil(1024)))
+        .collect::<Vec<String>>().join("\n");
+    ctx.send(CreateReply::default().embed(CreateEmbed::new()
+        .title("Sauvegardes de la base de données")
+        .description(liste)
+        .footer(CreateEmbedFooter::new(format!("{} sauvegarde(s) au total", sauvegardes.len())))
+    )).await?;
+    Ok(())
+}
+
+async fn autocomplete_sauvegarde<'a>(_ctx: Context<'_, DataType, ErrType>, partial: &'a str) -> impl Iterator<Item = String> + 'a {
+    sauvegardes::lister().unwrap_or_default().into_iter()
+        .map(|sauvegarde| sauvegarde.nom)
+        .filter(move |nom| nom.contains(partial))
+        .take(25)
+}
+
+/// Restaure une sauvegarde de la base de données, après aperçu des différences.
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn restaurer(ctx: Context<'_, DataType, ErrType>,
+                       #[description = "Sauvegarde à restaurer"] #[autocomplete = "autocomplete_sauvegarde"] sauvegarde: String) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let sauvegarde = sauvegardes::trouver(sauvegarde.as_str())?;
+    let fils = stockage::charger_yaml(sauvegarde.chemin.to_string_lossy().as_ref())?;
+    let differences = sauvegardes::comparer(&ctx.data().lock().await.database, &fils);
+    if differences.is_empty() {
+        ctx.say(format!("La sauvegarde {} est identique à la base actuelle.", sauvegarde.nom)).await?;
+        return Ok(());
+    }
+    let resume = format!("Restaurer la sauvegarde {} (<t:{}:f>) entraînerait {} changement(s) :", sauvegarde.nom, sauvegarde.date.timestamp(), differences.len());
+    if !confirmer(ctx, reponse_detaillee(resume, differences.join("\n"), "restauration.txt")).await? {
+        ctx.say("Restauration annulée.").await?;
+        return Ok(());
+    }
+
+    let bot = &mut ctx.data().lock().await;
+    /* L’état actuel est sauvegardé lui aussi, pour pouvoir revenir en arrière. */
+    stockage::sauvegarder(true)?;
+    /* Les fils absents de la base actuelle sont archivés eux aussi, pour que `/annuler` les retire. */
+    let mut ids: Vec<u64> = bot.database.keys().cloned().collect();
+    ids.extend(fils.iter().map(|fil| fil.get_id()).filter(|id| !bot.database.contains_key(id)));
+    let modification = audit::Modification::commencer(&bot.database, ids.clone(), ctx.author().name.as_str());
+    bot.archive(ids);
+    bot.database = fils.into_iter().map(|fil| (fil.get_id(), fil)).collect();
+    modification.terminer(&bot.database);
+    entete::actualiser(ctx.serenity_context(), bot).await?;
+    stockage::enregistrer(bot)?;
+    ctx.say(format!("Sauvegarde {} restaurée.", sauvegarde.nom)).await?;
+    Ok(())
+}
+
+/// Affiche les derniers avertissements et erreurs du bot.
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn journal(ctx: Context<'_, DataType, ErrType>,
+                     #[description = "Nombre de messages (10 par défaut)"] #[min = 1] #[max = 200] nombre: Option<usize>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let entrees = journalisation::dernieres(nombre.unwrap_or(10));
+    if entrees.is_empty() {
+        ctx.say("Aucun avertissement ni erreur depuis le lancement du bot.").await?;
+        return Ok(());
+    }
+    let details = entrees.iter().map(ToString::to_string).collect::<Vec<String>>().join("\n");
+    let resume = format!("{} dernier(s) avertissement(s) et erreur(s) :", entrees.len());
+    let reply = if resume.len() + details.len() < 1900 {
+        CreateReply::default().content(format!("{resume}\n```\n{details}\n```"))
+    } else {
+        reponse_detaillee(resume, details, "journal.txt")
+    };
+    ctx.send(reply.ephemeral(true)).await?;
+    Ok(())
+}
+
+fn decrire_releve(resultat: &Result<Compte, String>) -> String {
+    match resultat {
+        Ok(compte) => format!("{} ajouté(s), {} mis à jour", compte.ajoutes, compte.mis_a_jour),
+        Err(e) => format!("échec : {e}")
+    }
+}
+
+/// Relit immédiatement les flux RSS du forum.
+#[poise::command(slash_command)]
+pub async fn rafraichir(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    if let Err(restant) = releve::demander() {
+        ctx.send(CreateReply::default()
+            .content(format!("Les flux viennent d’être relus, réessayez dans {} s.", restant.as_secs() + 1))
+            .ephemeral(true)).await?;
+        return Ok(());
+    }
+    ctx.defer().await?;
+    let rapport = releve::relever(ctx.data()).await;
+    entete::actualiser(ctx.serenity_context(), &mut *ctx.data().lock().await).await?;
+    let lignes = rapport.iter()
+        .map(|(pole, resultat)| format!("{} {pole} : {}", pole.get_emoji(), decrire_releve(resultat)))
+        .collect::<Vec<String>>().join("\n");
+    ctx.say(format!("Flux relus.\n{lignes}")).await?;
+    Ok(())
+}
+
+/// Affiche l’état du relevé des flux RSS du forum.
+#[poise::command(slash_command)]
+pub async fn etat(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
+    let intervalle = config::get().releve.intervalle;
+    let etats = releve::etats();
+    let mut embed = CreateEmbed::new()
+        .title("État du relevé des flux")
+        .description(match releve::depuis_dernier() {
+            Some(depuis) => format!("Relevé automatique toutes les {intervalle} minute(s), prochain dans {} minute(s) environ.",
+                                    intervalle.saturating_sub(depuis.as_secs() / 60)),
+            None => format!("Relevé automatique toutes les {intervalle} minute(s) ; aucun relevé depuis le lancement du bot.")
+        });
+    for (pole, etat) in &etats {
+        embed = embed.field(format!("{} {pole}", pole.get_emoji()),
+                            format!("<t:{}:R>, en {} s : {}", etat.date.unix_timestamp(),
+                                    format!("{:.1}", etat.duree.as_secs_f64()).replace('.', ","), decrire_releve(&etat.resultat)),
+                            true);
+    }
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
 /// Affiche la page d’aide du bot.
 #[poise::command(slash_command, prefix_command)]
 pub async fn aide(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
+    passerelle::partager(ctx.serenity_context());
     ctx.send(CreateReply::default().embed(CreateEmbed::new()
         .title("Aide du Staffbot")
         .description("Les paramètres entre crochets sont optionnels, entre accolades obligatoires. La description des options est disponible en description des commandes slash.")
@@ -65,15 +848,37 @@ pub async fn aide(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
             `/annuler` : Annule la dernière modification effectuée.", false),
             ("Commandes de gestion et d'affichage de la liste",
              "`/ajouter {Nom} {Pole} {Statut} {URL}` : Ajoute manuellement un fil à la liste.\n\
+            `Applications > Enregistrer ce lien` (clic droit sur un message) : Enregistre les fils du forum dont le message contient le lien, après saisie pré-remplie de leurs informations.\n\
+            `/importer {Fichier}` : Importe des fils depuis un fichier CSV ou YAML (colonnes nom, url, pole, statut), après simulation et confirmation.\n\
             `/supprimer {Critère}` : Supprime un fil. Le Critère doit être assez fin pour aboutir à un unique fil. __**ATTENTION**__ : Il n'y a pas de confirmation, faites attention à ne pas vous tromper dans le Critère.\n", false),
             ("Commandes de modification des fils",
             "`/statut {Critère} {Statut}` : Modifie le statut d’un fil pour le nouveau statut.\n\
-            `/pole {Critère} {Pôle}` : Modifie le pôle d’un fil pour le nouveau pôle.", false),
+            `/pole {Critère} {Pôle}` : Modifie le pôle d’un fil pour le nouveau pôle.\n\
+            `/noter {Critère} {Texte}` : Ajoute une note au fil.\n\
+            `/echeance {Critère} [Date]` : Fixe la fin du vote d’un fil (JJ/MM/AAAA [HH:MM]), ou la retire si aucune date n’est donnée.\n\
+            `/assigner {Critère} {Membre} [Retirer]` : Ajoute (ou retire) un responsable du fil.\n\
+            Boutons « Pôle » et « Éditer » des embeds : Changent le pôle, ou le nom et le lien d’un fil (avec une note facultative), sans commande.\n\
+            `/statut-masse {Statut} [Pôle] [Statut actuel] [Jours]` : Change le statut de tous les fils correspondants (sans mise à jour depuis [Jours] jours si précisé), après confirmation.", false),
             ("Commandes de recherche",
              "`/rechercher {Critère}` : Affiche tous les fils contenant {Critère}.\n\
-            `/lister {Statut} {Pôle}` : Affiche la liste des fils avec le statut et du pôle demandés.", false),
+            `/lister {Statut} {Pôle}` : Affiche la liste des fils avec le statut et du pôle demandés.\n\
+            `/stats [Pôle] [Période] [Graphique]` : Affiche le nombre de fils par statut, les nouveaux fils et le temps passé en discussion et au vote.\n\
+            `/ordre-du-jour [Pôles] [Format]` : Prépare l’ordre du jour de la réunion (votes, développements récents, discussions en suspens), en message ou en syntaxe wikidot.", false),
+            ("Commandes de notification",
+             "`/abonner [Critère] [Pôle]` : Suit un fil ou tous les fils d’un pôle : changements de statut, notes et fin prochaine des votes.\n\
+            `/desabonner [Critère] [Pôle]` : Arrête de suivre un fil ou un pôle, ou tout si rien n’est précisé.\n\
+            `/notifications [Mode] [Silence]` : Choisit entre message privé et mention dans le salon des notifications, fixe des heures de silence et affiche vos abonnements.", false),
+            ("Commandes d'export",
+             "`/exporter {Format} [Statut] [Pôle]` : Exporte les fils demandés en CSV, JSON, Markdown, YAML ou syntaxe wikidot (à coller sur le forum).\n\
+            `/rapport [Statut] [Pôle]` : Envoie un rapport HTML imprimable des fils, regroupés par pôle.\n\
+            `/calendrier [Pôle]` : Envoie le calendrier (.ics) des fins de votes en cours.", false),
             ("Commandes d'entretien de la base de données (À utiliser avec précaution)",
-             "`/doublons` : Supprime les éventuels doublons.", false),
+             "`/doublons` : Supprime les éventuels doublons.\n\
+            `/sauvegardes` : Liste les sauvegardes de la base de données.\n\
+            `/restaurer {Sauvegarde}` : Restaure une sauvegarde après aperçu des différences et confirmation.\n\
+            `/journal [Nombre]` : Affiche les derniers avertissements et erreurs du bot.\n\
+            `/rafraichir` : Relit immédiatement les flux RSS du forum et indique les fils ajoutés et mis à jour par flux.\n\
+            `/etat` : Affiche la date, la durée et le résultat du dernier relevé de chaque flux.", false),
             ("Code source", "Disponible sur [Github](https://github.com/Fondation-SCP/staffbot).", false)
         ])
         .footer(CreateEmbedFooter::new("Version 0.1"))
@@ -83,5 +888,22 @@ pub async fn aide(ctx: Context<'_, DataType, ErrType>) -> Result<(), ErrType> {
 }
 
 pub fn command_list() -> Vec<Command<DataType, ErrType>> {
-    vec![ajouter(), pole(), statut(), lister(), aide(), alias("help", aide())]
+    vec![ajouter(), enregistrer_lien(), pole(), statut(), statut_masse(), noter(), echeance(), assigner(), lister(), stats(), ordre_du_jour(), exporter(), rapport(), calendrier(), abonner(), desabonner(), notifications(), importer(), sauvegardes(), restaurer(), journal(), rafraichir(), etat(), aide(), alias("help", aide())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selectionne_les_fils_du_statut_masse() {
+        let fils = stockage::tests::fixture("v2.yml");
+        let ids = |selection: &dyn Fn(&Fil) -> bool| fils.iter().filter(|fil| selection(fil)).map(|fil| fil.get_id()).collect::<Vec<u64>>();
+        /* Le fil déjà terminé n’est pas repris. */
+        assert_eq!(ids(&selection_masse(Status::Termine, None, None, None)), vec![1234567, 1111111]);
+        assert_eq!(ids(&selection_masse(Status::Termine, Some(Pole::Technique), Some(Status::Discussion), None)), vec![1111111]);
+        assert!(ids(&selection_masse(Status::Termine, Some(Pole::Legal), None, None)).is_empty());
+        /* Seuls les fils sans mise à jour depuis la limite (1721500000 pour les règles du chat). */
+        assert_eq!(ids(&selection_masse(Status::Termine, None, None, Some(1721500000))), vec![1234567]);
+    }
 }
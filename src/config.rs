@@ -0,0 +1,536 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use chrono::{NaiveTime, Weekday};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use fondabots_lib::ErrType;
+
+use crate::fil::fields::{Pole, Status};
+use crate::flux;
+
+pub const CHEMIN_PAR_DEFAUT: &str = "./staffbot-config.yml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Configuration du bot, lue dans `staffbot-config.yml`. Toutes les options sont facultatives.
+#[derive(Default, Debug)]
+pub struct Config {
+    /// Fichier de la base SQLite, qui remplace alors le fichier YAML : elle est remplie à partir de celui-ci au
+    /// premier lancement, puis seule lue et écrite par le bot. Les commandes propres à fondabots-lib (`/supprimer`…)
+    /// écrivent toujours le YAML : leurs changements gagnent la base SQLite au prochain enregistrement du bot
+    /// (au plus tard au relevé suivant). Si absent, seul le fichier YAML est utilisé.
+    pub sqlite: Option<String>,
+    pub sauvegardes: Sauvegardes,
+    pub audit: Audit,
+    pub journalisation: Journalisation,
+    /// API HTTP en lecture seule. Si absente, aucun serveur n’est lancé.
+    pub api: Option<Api>,
+    pub flux: Flux,
+    #[cfg_attr(not(feature = "graphiques"), allow(dead_code))]
+    pub stats: Stats,
+    pub ordre_du_jour: OrdreDuJour,
+    /// Salon forum Discord miroir des fils. Si absent, aucun post n’est créé.
+    pub forum: Option<Forum>,
+    pub notifications: Notifications,
+    pub annonces: Annonces,
+    pub apercus: Apercus,
+    pub edition: Edition,
+    pub releve: Releve,
+    pub verification: Verification
+}
+
+/// Section `sauvegardes` : copies horodatées du fichier de base de données.
+#[derive(Debug)]
+pub struct Sauvegardes {
+    pub dossier: String,
+    /// Nombre de sauvegardes conservées ; 0 désactive les sauvegardes.
+    pub conservation: usize,
+    /// Délai minimal en minutes entre deux sauvegardes automatiques.
+    pub intervalle: u64
+}
+
+impl Default for Sauvegardes {
+    fn default() -> Self {
+        Self {
+            dossier: "./sauvegardes".to_string(),
+            conservation: 48,
+            intervalle: 60
+        }
+    }
+}
+
+impl Sauvegardes {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            dossier: data["dossier"].as_str().map(str::to_string).unwrap_or(defaut.dossier),
+            conservation: data["conservation"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.conservation),
+            intervalle: data["intervalle"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.intervalle)
+        }
+    }
+}
+
+/// Section `audit` : où publier les modifications de la base de données.
+#[derive(Default, Debug)]
+pub struct Audit {
+    /// Salon Discord recevant un embed par modification.
+    pub salon: Option<u64>,
+    /// Fichier JSONL auquel chaque modification est ajoutée.
+    pub journal: Option<String>
+}
+
+impl Audit {
+    fn from_yaml(data: &Yaml) -> Self {
+        Self {
+            salon: data["salon"].as_i64().and_then(|n| n.try_into().ok()),
+            journal: data["journal"].as_str().map(str::to_string)
+        }
+    }
+}
+
+/// Format des messages de journalisation écrits sur la sortie d’erreur.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum FormatJournal {
+    /// Une ligne lisible par message.
+    #[default]
+    Humain,
+    /// Un objet JSON par ligne, pour les agrégateurs de journaux.
+    Json
+}
+
+/// Section `journalisation` : messages de fonctionnement du bot.
+#[derive(Debug)]
+pub struct Journalisation {
+    pub format: FormatJournal,
+    /// Niveau minimal des messages du bot : `error`, `warn`, `info`, `debug` ou `trace`.
+    pub niveau: String,
+    /// Nombre d’avertissements et d’erreurs gardés en mémoire pour `/journal`.
+    pub memoire: usize
+}
+
+impl Default for Journalisation {
+    fn default() -> Self {
+        Self {
+            format: FormatJournal::Humain,
+            niveau: "info".to_string(),
+            memoire: 200
+        }
+    }
+}
+
+impl Journalisation {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            format: match data["format"].as_str() {
+                Some("json") => FormatJournal::Json,
+                _ => defaut.format
+            },
+            niveau: data["niveau"].as_str().map(str::to_string).unwrap_or(defaut.niveau),
+            memoire: data["memoire"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.memoire)
+        }
+    }
+}
+
+/// Section `api` : serveur HTTP exposant la liste des fils en JSON.
+#[derive(Debug)]
+pub struct Api {
+    /// Adresse d’écoute, par exemple `127.0.0.1:8080`.
+    pub adresse: String,
+    /// Jeton attendu dans l’en-tête `Authorization: Bearer …`. Sans jeton, l’API est ouverte à tous.
+    pub jeton: Option<String>
+}
+
+impl Api {
+    pub const ADRESSE_PAR_DEFAUT: &'static str = "127.0.0.1:8080";
+
+    fn from_yaml(data: &Yaml) -> Option<Self> {
+        data.as_hash()?;
+        Some(Self {
+            adresse: data["adresse"].as_str().unwrap_or(Self::ADRESSE_PAR_DEFAUT).to_string(),
+            jeton: data["jeton"].as_str().map(str::to_string)
+        })
+    }
+}
+
+/// Section `flux` : flux RSS des changements de statut, servis par l’API et/ou écrits dans un dossier.
+#[derive(Debug)]
+pub struct Flux {
+    /// Lien du flux lui-même (site du staff…).
+    pub lien: String,
+    /// Dossier où écrire `tous.xml` et un fichier par pôle à chaque changement. Si absent, rien n’est écrit.
+    pub dossier: Option<String>,
+    /// Nombre de changements par flux.
+    pub taille: usize
+}
+
+impl Default for Flux {
+    fn default() -> Self {
+        Self {
+            lien: "http://commandemento5.wikidot.com/".to_string(),
+            dossier: None,
+            taille: 50
+        }
+    }
+}
+
+impl Flux {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            lien: data["lien"].as_str().map(str::to_string).unwrap_or(defaut.lien),
+            dossier: data["dossier"].as_str().map(str::to_string),
+            taille: data["taille"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.taille)
+        }
+    }
+}
+
+/// Section `stats` : graphiques de `/stats` (fonctionnalité « graphiques »).
+#[derive(Debug)]
+pub struct Stats {
+    /// Police TrueType utilisée pour le texte des graphiques.
+    pub police: String
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            police: "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf".to_string()
+        }
+    }
+}
+
+impl Stats {
+    fn from_yaml(data: &Yaml) -> Self {
+        Self {
+            police: data["police"].as_str().map(str::to_string).unwrap_or(Self::default().police)
+        }
+    }
+}
+
+/// Section `ordre_du_jour` : contenu de `/ordre-du-jour` et publication hebdomadaire.
+#[derive(Debug)]
+pub struct OrdreDuJour {
+    /// Âge maximal en jours d’un passage en développement pour figurer à l’ordre du jour.
+    pub recents: i64,
+    /// Nombre de jours sans mise à jour au-delà duquel une discussion est considérée comme en suspens.
+    pub anciennes: i64,
+    /// Publication automatique chaque semaine. Si absente, l’ordre du jour n’est préparé qu’à la demande.
+    pub planification: Option<Planification>
+}
+
+impl Default for OrdreDuJour {
+    fn default() -> Self {
+        Self {
+            recents: 14,
+            anciennes: 30,
+            planification: None
+        }
+    }
+}
+
+impl OrdreDuJour {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            recents: data["recents"].as_i64().unwrap_or(defaut.recents),
+            anciennes: data["anciennes"].as_i64().unwrap_or(defaut.anciennes),
+            planification: Planification::from_yaml(&data["planification"])
+        }
+    }
+}
+
+/// Sous-section `planification` : salon, jour (`lundi`…) et heure de Paris (`HH:MM`) de la publication.
+/// Une valeur invalide désactive la publication.
+#[derive(Debug)]
+pub struct Planification {
+    pub salon: u64,
+    pub jour: Weekday,
+    pub heure: NaiveTime,
+    /// Ordre du jour en syntaxe wikidot plutôt qu’en message Discord.
+    pub wikidot: bool
+}
+
+impl Planification {
+    fn from_yaml(data: &Yaml) -> Option<Self> {
+        let jour = match data["jour"].as_str().unwrap_or("lundi").to_lowercase().as_str() {
+            "lundi" => Weekday::Mon,
+            "mardi" => Weekday::Tue,
+            "mercredi" => Weekday::Wed,
+            "jeudi" => Weekday::Thu,
+            "vendredi" => Weekday::Fri,
+            "samedi" => Weekday::Sat,
+            "dimanche" => Weekday::Sun,
+            _ => return None
+        };
+        Some(Self {
+            salon: data["salon"].as_i64()?.try_into().ok()?,
+            jour,
+            heure: NaiveTime::parse_from_str(data["heure"].as_str().unwrap_or("09:00"), "%H:%M").ok()?,
+            wikidot: data["wikidot"].as_bool().unwrap_or(false)
+        })
+    }
+}
+
+/// Section `forum` : salon forum Discord où chaque nouveau fil a son post. Les étiquettes du salon
+/// portant le nom d’un statut ou d’un pôle sont appliquées aux posts et tenues à jour.
+#[derive(Debug)]
+pub struct Forum {
+    pub salon: u64
+}
+
+impl Forum {
+    fn from_yaml(data: &Yaml) -> Option<Self> {
+        Some(Self {
+            salon: data["salon"].as_i64()?.try_into().ok()?
+        })
+    }
+}
+
+/// Section `notifications` : abonnements des membres du staff aux fils et aux pôles.
+#[derive(Debug)]
+pub struct Notifications {
+    /// Fichier YAML des abonnements, distinct de la base de données.
+    pub fichier: String,
+    /// Salon des notifications pour les membres qui préfèrent y être mentionnés plutôt qu’en message privé.
+    /// Si absent, toutes les notifications sont envoyées en message privé.
+    pub salon: Option<u64>,
+    /// Nombre d’heures avant la fin d’un vote auquel les abonnés en sont avertis.
+    pub rappel: i64
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            fichier: "./staffbot-abonnements.yml".to_string(),
+            salon: None,
+            rappel: 24
+        }
+    }
+}
+
+impl Notifications {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            fichier: data["fichier"].as_str().map(str::to_string).unwrap_or(defaut.fichier),
+            salon: data["salon"].as_i64().and_then(|n| n.try_into().ok()),
+            rappel: data["rappel"].as_i64().unwrap_or(defaut.rappel)
+        }
+    }
+}
+
+/// Section `annonces` : annonce des nouveaux fils et des changements de statut dans le salon de leur pôle.
+/// Sous-section `poles`, indexée par identifiant de pôle (`technique`, `retd`…) ; les pôles absents ne sont pas annoncés.
+#[derive(Debug)]
+pub struct Annonces {
+    pub poles: Vec<(Pole, AnnoncesPole)>,
+    /// Nombre maximal d’annonces détaillées par salon sur la période ; au-delà, elles sont regroupées
+    /// dans un message récapitulatif, sans mention.
+    pub limite: usize,
+    /// Durée de la période, en minutes.
+    pub periode: i64
+}
+
+impl Default for Annonces {
+    fn default() -> Self {
+        Self {
+            poles: Vec::new(),
+            limite: 5,
+            periode: 10
+        }
+    }
+}
+
+impl Annonces {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            poles: data["poles"].as_hash().map(|poles| poles.iter().filter_map(|(pole, annonces)| Some((
+                flux::pole_depuis_identifiant(pole.as_str()?)?,
+                AnnoncesPole::from_yaml(annonces)?
+            ))).collect()).unwrap_or_default(),
+            limite: data["limite"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.limite),
+            periode: data["periode"].as_i64().unwrap_or(defaut.periode)
+        }
+    }
+
+    pub fn pole(&self, pole: &Pole) -> Option<&AnnoncesPole> {
+        self.poles.iter().find(|(autre, _)| autre == pole).map(|(_, annonces)| annonces)
+    }
+}
+
+#[derive(Debug)]
+pub struct AnnoncesPole {
+    pub salon: u64,
+    /// Rôle mentionné dans chaque annonce.
+    pub role: Option<u64>,
+    /// Annoncer les nouveaux fils.
+    pub nouveaux: bool,
+    /// Statuts dont le passage est annoncé (`Vote` par défaut).
+    pub statuts: Vec<Status>
+}
+
+impl AnnoncesPole {
+    fn from_yaml(data: &Yaml) -> Option<Self> {
+        Some(Self {
+            salon: data["salon"].as_i64()?.try_into().ok()?,
+            role: data["role"].as_i64().and_then(|n| n.try_into().ok()),
+            nouveaux: data["nouveaux"].as_bool().unwrap_or(true),
+            statuts: match data["statuts"].as_vec() {
+                Some(statuts) => statuts.iter().filter_map(|statut| statut.as_str()?.parse().ok()).collect(),
+                None => vec![Status::Vote]
+            }
+        })
+    }
+}
+
+/// Section `apercus` : réponse aux liens de fils du forum postés dans les salons du staff, par l’embed
+/// et les boutons du fil. Nécessite l’intention privilégiée « Message Content » du bot.
+///
+/// Limite : fondabots-lib ne donne accès ni à l’évènement `ready` ni aux messages reçus, et le contexte de
+/// la passerelle n’est obtenu que par une commande ou un clic sur un bouton (voir `passerelle`). Après chaque
+/// démarrage, aucun aperçu n’est donc envoyé tant que personne n’a utilisé de commande ou de bouton du bot.
+#[derive(Debug)]
+pub struct Apercus {
+    /// Salons où les liens sont détectés. Si vide, aucun aperçu n’est envoyé.
+    pub salons: Vec<u64>,
+    /// Délai en minutes avant qu’un même fil puisse de nouveau recevoir un aperçu dans un salon.
+    pub delai: i64
+}
+
+impl Default for Apercus {
+    fn default() -> Self {
+        Self {
+            salons: Vec::new(),
+            delai: 60
+        }
+    }
+}
+
+impl Apercus {
+    fn from_yaml(data: &Yaml) -> Self {
+        Self {
+            salons: data["salons"].as_vec().map(|salons| salons.iter()
+                .filter_map(|salon| salon.as_i64().and_then(|n| n.try_into().ok())).collect()).unwrap_or_default(),
+            delai: data["delai"].as_i64().unwrap_or(Self::default().delai)
+        }
+    }
+}
+
+/// Section `edition` : qui peut modifier le nom, le lien et le pôle d’un fil depuis son embed, importer des fils
+/// et changer le statut de plusieurs fils à la fois.
+#[derive(Default, Debug)]
+pub struct Edition {
+    /// Rôles autorisés. Si vide, tous les membres voyant l’embed le peuvent.
+    pub roles: Vec<u64>
+}
+
+impl Edition {
+    fn from_yaml(data: &Yaml) -> Self {
+        Self {
+            roles: data["roles"].as_vec().map(|roles| roles.iter()
+                .filter_map(|role| role.as_i64().and_then(|n| n.try_into().ok())).collect()).unwrap_or_default()
+        }
+    }
+}
+
+/// Section `releve` : relevé des flux RSS du forum.
+#[derive(Debug)]
+pub struct Releve {
+    /// Intervalle en minutes entre deux relevés automatiques.
+    pub intervalle: u64,
+    /// Délai en secondes entre deux relevés demandés à la main (`/rafraichir`, en-tête des affichans).
+    pub delai: u64
+}
+
+impl Default for Releve {
+    fn default() -> Self {
+        Self {
+            intervalle: 10,
+            delai: 60
+        }
+    }
+}
+
+impl Releve {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            intervalle: data["intervalle"].as_i64().and_then(|n| n.try_into().ok()).filter(|n| *n > 0).unwrap_or(defaut.intervalle),
+            delai: data["delai"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.delai)
+        }
+    }
+}
+
+/// Section `verification` : vérification périodique que les fils en cours existent toujours sur le forum.
+#[derive(Debug)]
+pub struct Verification {
+    /// Intervalle en heures entre deux vérifications ; 0 les désactive.
+    pub intervalle: u64,
+    /// Pause en millisecondes entre deux fils, pour ménager le forum.
+    pub pause: u64
+}
+
+impl Default for Verification {
+    fn default() -> Self {
+        Self {
+            intervalle: 24,
+            pause: 1000
+        }
+    }
+}
+
+impl Verification {
+    fn from_yaml(data: &Yaml) -> Self {
+        let defaut = Self::default();
+        Self {
+            intervalle: data["intervalle"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.intervalle),
+            pause: data["pause"].as_i64().and_then(|n| n.try_into().ok()).unwrap_or(defaut.pause)
+        }
+    }
+}
+
+impl Config {
+    pub fn charger(chemin: &str) -> Result<Self, ErrType> {
+        if !Path::new(chemin).exists() {
+            return Ok(Self::default());
+        }
+        let documents = YamlLoader::load_from_str(fs::read_to_string(chemin)?.as_str())
+            .map_err(|e| ErrType::YamlParseError(e.to_string()))?;
+        Ok(Self::from_yaml(documents.first().unwrap_or(&Yaml::Null)))
+    }
+
+    fn from_yaml(data: &Yaml) -> Self {
+        Self {
+            sqlite: data["sqlite"].as_str().map(str::to_string),
+            sauvegardes: Sauvegardes::from_yaml(&data["sauvegardes"]),
+            audit: Audit::from_yaml(&data["audit"]),
+            journalisation: Journalisation::from_yaml(&data["journalisation"]),
+            api: Api::from_yaml(&data["api"]),
+            flux: Flux::from_yaml(&data["flux"]),
+            stats: Stats::from_yaml(&data["stats"]),
+            ordre_du_jour: OrdreDuJour::from_yaml(&data["ordre_du_jour"]),
+            forum: Forum::from_yaml(&data["forum"]),
+            notifications: Notifications::from_yaml(&data["notifications"]),
+            annonces: Annonces::from_yaml(&data["annonces"]),
+            apercus: Apercus::from_yaml(&data["apercus"]),
+            edition: Edition::from_yaml(&data["edition"]),
+            releve: Releve::from_yaml(&data["releve"]),
+            verification: Verification::from_yaml(&data["verification"])
+        }
+    }
+}
+
+/// Rend la configuration accessible au reste du bot. À appeler une seule fois, au démarrage.
+pub fn init(config: Config) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("La configuration a déjà été initialisée.");
+    }
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
@@ -0,0 +1,27 @@
+use std::sync::{Arc, OnceLock};
+
+use poise::serenity_prelude as serenity;
+use serenity::all::{ChannelId, CreateMessage, Http, Message};
+
+use fondabots_lib::ErrType;
+
+/// Client HTTP Discord utilisable en dehors des interactions (flux RSS, tâches de fond…).
+static HTTP: OnceLock<Arc<Http>> = OnceLock::new();
+
+pub fn init(token: &str) {
+    if HTTP.set(Arc::new(Http::new(token))).is_err() {
+        tracing::warn!("Le client HTTP Discord a déjà été initialisé.");
+    }
+}
+
+pub fn http() -> Option<Arc<Http>> {
+    HTTP.get().cloned()
+}
+
+/// Envoie un message dans un salon. Ne fait rien si le bot n’a pas été lancé (export hors ligne…).
+pub async fn envoyer(salon: u64, message: CreateMessage) -> Result<Option<Message>, ErrType> {
+    match http() {
+        Some(http) => Ok(Some(ChannelId::new(salon).send_message(&http, message).await?)),
+        None => Ok(None)
+    }
+}
@@ -0,0 +1,157 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::{ButtonStyle, ChannelId, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage, Http, Timestamp};
+use serenity::Context as SerenityContext;
+use strum::IntoEnumIterator;
+use tokio::sync::Mutex;
+
+use fondabots_lib::{Bot, ErrType, Object};
+
+use crate::discord;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::releve;
+use crate::fil::Fil;
+use crate::stockage;
+
+/// Salons d’affichage, avec le statut des fils qui y sont affichés.
+pub const AFFICHANS: [(u64, Status); 1] = [
+    (1265001559373119493, Status::Vote)
+];
+
+/// Titre de l’en-tête, qui permet de le retrouver parmi les messages épinglés du salon.
+const TITRE: &str = "Récapitulatif des fils";
+
+/// Nombre d’échéances de vote affichées dans l’en-tête.
+const ECHEANCES: usize = 5;
+
+/// Les en-têtes sont mis à jour l’un après l’autre, pour ne pas en créer deux dans un même salon.
+static PUBLICATION: Mutex<()> = Mutex::const_new(());
+
+fn compte(lignes: Vec<(String, usize)>) -> String {
+    let lignes: Vec<String> = lignes.into_iter().filter(|(_, nombre)| *nombre > 0)
+        .map(|(nom, nombre)| format!("{nom} : **{nombre}**")).collect();
+    if lignes.is_empty() { "Aucun".to_string() } else { lignes.join("\n") }
+}
+
+/// Récapitulatif des fils en cours : nombre par pôle et par statut, votes les plus proches de leur fin.
+fn embed<'a>(fils: impl Iterator<Item = &'a Fil>, statut: &Status, maintenant: &Timestamp) -> CreateEmbed {
+    let en_cours: Vec<&Fil> = fils.filter(|fil| fil.status != Status::Termine).collect();
+    let affiches = en_cours.iter().filter(|fil| fil.status == *statut).count();
+    let par_pole = Pole::iter().map(|pole| (format!("{} {pole}", pole.get_emoji()),
+                                            en_cours.iter().filter(|fil| fil.pole == pole).count())).collect();
+    let par_statut = Status::iter().map(|statut| (format!("{} {statut}", statut.get_badge()),
+                                                  en_cours.iter().filter(|fil| fil.status == statut).count())).collect();
+    let mut votes: Vec<(&Fil, &Timestamp)> = en_cours.iter()
+        .filter_map(|fil| fil.echeance.as_ref().filter(|_| fil.status == Status::Vote).map(|echeance| (*fil, echeance)))
+        .collect();
+    votes.sort_by_key(|(_, echeance)| echeance.unix_timestamp());
+    let echeances: Vec<String> = votes.iter().take(ECHEANCES).map(|(fil, echeance)| {
+        let nom: String = fil.get_name().chars().take(80).collect();
+        format!("[{nom}](<{}>) : <t:{}:R>", fil.get_lien(), echeance.unix_timestamp())
+    }).collect();
+    CreateEmbed::new()
+        .title(TITRE)
+        .description(format!("{} fil(s) en cours, dont {affiches} affiché(s) dans ce salon ({statut}).", en_cours.len()))
+        .field("Par pôle", compte(par_pole), true)
+        .field("Par statut", compte(par_statut), true)
+        .field("Échéances les plus proches", if echeances.is_empty() { "Aucune".to_string() } else { echeances.join("\n") }, false)
+        .field("Dernier rafraîchissement", format!("<t:{0}:f> (<t:{0}:R>)", maintenant.unix_timestamp()), false)
+        .footer(CreateEmbedFooter::new("Le bouton relit immédiatement les flux RSS du forum."))
+        .timestamp(maintenant)
+}
+
+fn bouton(salon: u64) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("r-{salon}")).style(ButtonStyle::Primary).label("Rafraîchir").emoji('🔄')
+    ])
+}
+
+/// Modifie l’en-tête épinglé du salon, ou le publie et l’épingle s’il n’existe pas encore.
+async fn publier(http: &Http, salon: u64, embed: CreateEmbed) -> Result<(), ErrType> {
+    let salon_id = ChannelId::new(salon);
+    let epingles = salon_id.pins(http).await?;
+    let existant = epingles.into_iter().find(|message| message.author.bot
+        && message.embeds.first().is_some_and(|embed| embed.title.as_deref() == Some(TITRE)));
+    match existant {
+        Some(mut message) => message.edit(http, EditMessage::new().embed(embed).components(vec![bouton(salon)])).await?,
+        None => salon_id.send_message(http, CreateMessage::new().embed(embed).components(vec![bouton(salon)])).await?.pin(http).await?
+    }
+    Ok(())
+}
+
+/// Met à jour l’en-tête de chaque salon d’affichage, en tâche de fond.
+///
+/// L’en-tête suit les rafraîchissements décidés par le bot (`actualiser`, `signaler`). Ceux que
+/// fondabots-lib fait d’elle-même, après ses propres commandes ou à son cycle, ne sont pas couverts :
+/// l’en-tête est alors à jour au prochain rafraîchissement du bot.
+pub fn mettre_a_jour(bot: &Bot<Fil>) {
+    let maintenant = Timestamp::now();
+    let embeds: Vec<(u64, CreateEmbed)> = AFFICHANS.iter()
+        .map(|(salon, statut)| (*salon, embed(bot.database.values(), statut, &maintenant)))
+        .collect();
+    tokio::spawn(async move {
+        let Some(http) = discord::http() else {
+            return;
+        };
+        let _verrou = PUBLICATION.lock().await;
+        for (salon, embed) in embeds {
+            if let Err(e) = publier(&http, salon, embed).await {
+                tracing::error!(erreur = %e, salon, "Erreur lors de la mise à jour de l’en-tête de l’affichan");
+            }
+        }
+    });
+}
+
+/// Demande à fondabots-lib de rafraîchir les salons d’affichage à son prochain cycle, et met à jour leur en-tête.
+pub fn signaler(bot: &mut Bot<Fil>) {
+    bot.update_affichans = true;
+    mettre_a_jour(bot);
+}
+
+/// Met à jour les salons d’affichage et leur en-tête.
+pub async fn actualiser(ctx: &SerenityContext, bot: &mut Bot<Fil>) -> Result<(), ErrType> {
+    bot.update_affichans(ctx).await?;
+    mettre_a_jour(bot);
+    Ok(())
+}
+
+/// Répond au bouton « Rafraîchir » d’un en-tête : les flux sont relus en tâche de fond, la base
+/// étant verrouillée pendant le traitement du bouton.
+pub async fn rafraichir(ctx: &SerenityContext, interaction: &ComponentInteraction) -> Result<(), ErrType> {
+    if let Err(restant) = releve::demander() {
+        interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+            .content(format!("Les flux viennent d’être relus, réessayez dans {} s.", restant.as_secs() + 1))
+            .ephemeral(true))).await?;
+        return Ok(());
+    }
+    interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        let Some(bot) = stockage::bot() else {
+            return;
+        };
+        releve::relever(&bot).await;
+        let mut bot = bot.lock().await;
+        if let Err(e) = actualiser(&ctx, &mut bot).await {
+            tracing::error!(erreur = %e, "Erreur lors de la mise à jour des affichans");
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recapitule_les_fils_en_cours() {
+        let fils = stockage::tests::fixture("v2.yml");
+        let embed = serde_json::to_value(embed(fils.iter(), &Status::Vote, &Timestamp::now())).unwrap();
+        let champs = embed["fields"].as_array().unwrap();
+        let valeur = |i: usize| champs[i]["value"].as_str().unwrap().to_string();
+        /* La charte, terminée, n’est pas comptée ; les pôles et statuts sans fil ne sont pas listés. */
+        assert_eq!(embed["description"], "2 fil(s) en cours, dont 1 affiché(s) dans ce salon (Vote).");
+        assert_eq!(valeur(0), "🛠️ Technique : **2**");
+        assert_eq!(valeur(1), "💬 Discussion : **1**\n🗳️ Vote : **1**");
+        assert_eq!(valeur(2), "[Refonte du guide](<http://commandemento5.wikidot.com/forum/t-1234567/refonte-du-guide>) : <t:1721600000:R>");
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+use poise::ChoiceParameter;
+use serde_json::{Map, Number, Value};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use yaml_rust2::{Yaml, YamlEmitter};
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+use crate::wikidot;
+
+/// Erreur de génération d’un export, présentée avec son format.
+fn erreur(format: &str, e: impl Display) -> ErrType {
+    io::Error::other(format!("Erreur lors de l’export {format} : {e}")).into()
+}
+
+#[derive(EnumIter, Clone, PartialEq, Eq, ChoiceParameter, Debug)]
+pub enum Format {
+    #[name = "CSV"]
+    Csv,
+    #[name = "JSON"]
+    Json,
+    Markdown,
+    #[name = "YAML"]
+    Yaml,
+    /// Syntaxe wikidot, à coller dans un message du forum.
+    Wikidot
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Markdown => "md",
+            Format::Yaml => "yml",
+            Format::Wikidot => "txt"
+        }
+    }
+
+    pub fn exporter(&self, fils: &[&Fil]) -> Result<String, ErrType> {
+        match self {
+            Format::Csv => to_csv(fils),
+            Format::Json => to_json(fils),
+            Format::Markdown => Ok(to_markdown(fils)),
+            Format::Yaml => to_yaml(fils),
+            Format::Wikidot => Ok(to_wikidot(fils))
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = ErrType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for e in Self::iter() {
+            if e.to_string().to_lowercase() == s.to_lowercase() || e.extension() == s.to_lowercase() {
+                return Ok(e);
+            }
+        }
+        Err(ErrType::ObjectNotFound(format!("Format {s} inexistant.")))
+    }
+}
+
+/// Trie les fils par pôle puis par nom, pour que les exports soient stables d’une fois sur l’autre.
+pub fn trier<'a>(fils: impl Iterator<Item = &'a Fil>) -> Vec<&'a Fil> {
+    let poles: Vec<Pole> = Pole::iter().collect();
+    let mut fils: Vec<&Fil> = fils.collect();
+    fils.sort_by_key(|fil| (poles.iter().position(|pole| *pole == fil.pole), fil.get_name().to_lowercase()));
+    fils
+}
+
+fn serialize_with_id(fil: &Fil) -> Yaml {
+    let mut yaml_out = yaml_rust2::yaml::Hash::new();
+    yaml_out.insert(Yaml::String("id".to_string()), Yaml::Integer(fil.get_id() as i64));
+    if let Yaml::Hash(hash) = fil.serialize() {
+        yaml_out.extend(hash);
+    }
+    Yaml::Hash(yaml_out)
+}
+
+fn yaml_to_json(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::Real(s) => s.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number).unwrap_or(Value::String(s.clone())),
+        Yaml::Integer(i) => Value::Number(Number::from(*i)),
+        Yaml::String(s) => Value::String(s.clone()),
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Array(a) => Value::Array(a.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(h) => {
+            let mut map = Map::new();
+            for (k, v) in h {
+                let key = match k {
+                    Yaml::String(s) => s.clone(),
+                    Yaml::Integer(i) => i.to_string(),
+                    other => yaml_to_json(other).to_string()
+                };
+                map.insert(key, yaml_to_json(v));
+            }
+            Value::Object(map)
+        }
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Value::Null
+    }
+}
+
+/// Représentation JSON d’un fil : ses champs sérialisés, précédés de son identifiant.
+pub fn fil_to_json(fil: &Fil) -> Value {
+    yaml_to_json(&serialize_with_id(fil))
+}
+
+fn to_json(fils: &[&Fil]) -> Result<String, ErrType> {
+    let fils = Value::Array(fils.iter().map(|fil| fil_to_json(fil)).collect());
+    serde_json::to_string_pretty(&fils).map_err(|e| erreur("JSON", e))
+}
+
+fn to_yaml(fils: &[&Fil]) -> Result<String, ErrType> {
+    let mut out = String::new();
+    YamlEmitter::new(&mut out).dump(&Yaml::Array(fils.iter().map(|fil| fil.serialize()).collect()))
+        .map_err(|e| erreur("YAML", e))?;
+    Ok(out + "\n")
+}
+
+fn to_csv(fils: &[&Fil]) -> Result<String, ErrType> {
+    let lignes: Vec<Map<String, Value>> = fils.iter().filter_map(|fil| match fil_to_json(fil) {
+        Value::Object(map) => Some(map),
+        _ => None
+    }).collect();
+
+    /* Les colonnes sont l’union des champs sérialisés, dans l’ordre de première apparition. */
+    let mut colonnes: Vec<String> = Vec::new();
+    for ligne in &lignes {
+        for cle in ligne.keys() {
+            if !colonnes.contains(cle) {
+                colonnes.push(cle.clone());
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&colonnes).map_err(|e| erreur("CSV", e))?;
+    for ligne in &lignes {
+        writer.write_record(colonnes.iter().map(|colonne| match ligne.get(colonne) {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(v) => v.to_string()
+        })).map_err(|e| erreur("CSV", e))?;
+    }
+    let octets = writer.into_inner().map_err(|e| erreur("CSV", e))?;
+    String::from_utf8(octets).map_err(|e| erreur("CSV", e))
+}
+
+fn to_markdown(fils: &[&Fil]) -> String {
+    let mut out = String::from("# Fils du staff\n");
+    for pole in Pole::iter() {
+        let fils_pole: Vec<&&Fil> = fils.iter().filter(|fil| fil.pole == pole).collect();
+        if fils_pole.is_empty() {
+            continue;
+        }
+        out += format!("\n## {pole}\n\n").as_str();
+        for fil in fils_pole {
+            out += format!("- [{}]({}) — {}\n", fil.get_name().replace(['[', ']'], ""), fil.get_lien(), fil.status).as_str();
+        }
+    }
+    out
+}
+
+fn to_wikidot(fils: &[&Fil]) -> String {
+    let mut out = String::from("+ Fils du staff\n");
+    for pole in Pole::iter() {
+        let fils_pole: Vec<&&Fil> = fils.iter().filter(|fil| fil.pole == pole).collect();
+        if fils_pole.is_empty() {
+            continue;
+        }
+        out += format!("\n++ {}\n\n||~ Fil ||~ Statut ||~ Fin du vote ||~ Responsables ||\n", wikidot::echapper(pole.to_string().as_str())).as_str();
+        for fil in fils_pole {
+            let echeance = match (&fil.status, &fil.echeance) {
+                (Status::Vote, Some(echeance)) => wikidot::date(echeance),
+                _ => String::new()
+            };
+            out += format!("|| {} || {} || {echeance} || {} ||\n", wikidot::lien(fil.get_lien(), fil.get_name()),
+                           wikidot::echapper(fil.status.to_string().as_str()),
+                           wikidot::echapper(fil.get_responsables().join(", ").as_str())).as_str();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stockage;
+
+    use super::*;
+
+    #[test]
+    fn lit_le_format() {
+        assert_eq!(Format::from_str("json").unwrap(), Format::Json);
+        assert_eq!(Format::from_str("md").unwrap(), Format::Markdown);
+        assert!(Format::from_str("pdf").is_err());
+    }
+
+    #[test]
+    fn exporte_les_fils() {
+        let fils = stockage::tests::fixture("v2.yml");
+        let fils = trier(fils.iter());
+        /* Technique avant Légal, puis par nom. */
+        assert_eq!(fils.iter().map(|fil| fil.get_id()).collect::<Vec<u64>>(), vec![1111111, 1234567, 7654321]);
+
+        let csv = Format::Csv.exporter(&fils).unwrap();
+        let mut lignes = csv.lines();
+        let colonnes: Vec<&str> = lignes.next().unwrap().split(',').collect();
+        assert_eq!(colonnes[..3], ["id", "version", "nom"]);
+        /* Les colonnes des champs facultatifs restent vides pour les fils qui ne les ont pas. */
+        assert!(colonnes.contains(&"echeance"));
+        assert_eq!(lignes.count(), 3);
+
+        let json: Value = serde_json::from_str(Format::Json.exporter(&fils).unwrap().as_str()).unwrap();
+        assert_eq!(json[1]["id"], 1234567);
+        assert_eq!(json[1]["responsables"], serde_json::json!(["alice", "carole"]));
+
+        let markdown = Format::Markdown.exporter(&fils).unwrap();
+        assert!(markdown.find("## Technique").unwrap() < markdown.find("## Légal").unwrap());
+        assert!(markdown.contains("- [Refonte du guide](http://commandemento5.wikidot.com/forum/t-1234567/refonte-du-guide) — Vote\n"));
+
+        let chemin = std::env::temp_dir().join(format!("staffbot-export-{}.yml", std::process::id()));
+        std::fs::write(&chemin, Format::Yaml.exporter(&fils).unwrap()).unwrap();
+        let relus = stockage::charger_yaml(chemin.to_str().unwrap()).unwrap();
+        std::fs::remove_file(chemin).unwrap();
+        assert_eq!(relus.iter().collect::<Vec<&Fil>>(), fils);
+    }
+}
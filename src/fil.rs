@@ -1,18 +1,24 @@
 use std::str::FromStr;
+use std::sync::LazyLock;
 
-use chrono::DateTime;
 use poise::serenity_prelude as serenity;
 use regex::Regex;
-use rss::Channel;
 use serenity::all::{ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, EditMessage, Timestamp};
 use serenity::Context as SerenityContext;
 use yaml_rust2::{Yaml, yaml};
 
 use fields::Pole;
 use fields::Status;
-use fondabots_lib::{Bot, DataType, ErrType, Object, try_loop};
+use fondabots_lib::{Bot, DataType, ErrType, Object};
+use suivi::{Note, Transition};
 
+use crate::{audit, entete, passerelle, stockage};
+
+pub mod edition;
 pub mod fields;
+pub mod releve;
+pub mod schema;
+pub mod suivi;
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Fil {
@@ -21,8 +27,47 @@ pub struct Fil {
     pub pole: Pole,
     pub status: Status,
     pub last_update: Timestamp,
+    /// Date d’ouverture du fil, inconnue pour les fils enregistrés avant son introduction.
+    pub creation: Option<Timestamp>,
+    /// Fin du vote, pour les fils au vote.
+    pub echeance: Option<Timestamp>,
+    /// Membres du staff chargés du fil.
+    responsables: Vec<String>,
+    /// Post du salon forum Discord consacré au fil, s’il a été créé.
+    pub post: Option<u64>,
+    /// Nombre de réponses du fil sur le forum, lu dans son flux RSS. Le flux ne listant que les derniers
+    /// messages, c’est un minimum.
+    pub reponses: Option<u32>,
     id: u64,
-    modified: bool
+    modified: bool,
+    historique: Vec<Transition>,
+    notes: Vec<Note>
+}
+
+/// Catégories du forum suivies par flux RSS, avec le pôle de leurs fils.
+pub const CATEGORIES: [(Pole, u64); 7] = [
+    (Pole::Interne, 6827498),
+    (Pole::Ambassade, 1905805),
+    (Pole::RetD, 6827479),
+    (Pole::Technique, 6827478),
+    (Pole::Evenementiel, 6827484),
+    (Pole::Creation, 7643353),
+    (Pole::Legal, 1905799)
+];
+
+/// Longueurs maximales des textes des embeds, sous les limites de Discord (256 pour le titre,
+/// 1024 par champ, 6000 en tout).
+const LIMITE_TITRE: usize = 256;
+const LIMITE_CHAMP: usize = 1024;
+const LIMITE_NOTE: usize = 300;
+
+/// Coupe un texte à `limite` caractères, points de suspension compris.
+fn tronquer(texte: &str, limite: usize) -> String {
+    if texte.chars().count() <= limite {
+        texte.to_string()
+    } else {
+        texte.chars().take(limite - 1).collect::<String>() + "…"
+    }
 }
 
 impl Fil {
@@ -34,8 +79,98 @@ impl Fil {
             id: Self::find_id(&lien).unwrap(),
             lien,
             last_update: Timestamp::now(),
-            modified: false
+            creation: Some(Timestamp::now()),
+            echeance: None,
+            responsables: Vec::new(),
+            post: None,
+            reponses: None,
+            modified: false,
+            historique: Vec::new(),
+            notes: Vec::new()
+        }
+    }
+
+    /// Change le statut du fil en gardant la trace du changement dans son historique.
+    pub fn set_status(&mut self, status: Status, auteur: Option<String>) {
+        if status != self.status {
+            self.historique.push(Transition {
+                de: self.status.clone(),
+                vers: status.clone(),
+                date: Timestamp::now(),
+                auteur,
+                nom: Some(self.name.clone()),
+                pole: Some(self.pole.clone())
+            });
+            self.status = status;
+        }
+    }
+
+    pub fn ajouter_note(&mut self, auteur: String, texte: String) {
+        self.notes.push(Note {
+            auteur,
+            date: Timestamp::now(),
+            texte
+        });
+    }
+
+    /// Ajoute un responsable au fil. Renvoie faux s’il l’était déjà.
+    pub fn assigner(&mut self, responsable: String) -> bool {
+        if self.responsables.contains(&responsable) {
+            return false;
+        }
+        self.responsables.push(responsable);
+        true
+    }
+
+    /// Retire un responsable du fil. Renvoie faux s’il ne l’était pas.
+    pub fn desassigner(&mut self, responsable: &str) -> bool {
+        let avant = self.responsables.len();
+        self.responsables.retain(|nom| nom != responsable);
+        self.responsables.len() != avant
+    }
+
+    pub fn get_responsables(&self) -> &Vec<String> {
+        &self.responsables
+    }
+
+    pub fn get_notes(&self) -> &Vec<Note> {
+        &self.notes
+    }
+
+    pub fn get_historique(&self) -> &Vec<Transition> {
+        &self.historique
+    }
+
+    pub fn get_lien(&self) -> &String {
+        &self.lien
+    }
+
+    /// Flux RSS des messages du fil sur le forum.
+    pub fn flux(&self) -> Option<String> {
+        self.lien.find("/forum/").map(|fin| format!("{}/feed/forum/t-{}.xml", &self.lien[..fin], self.id))
+    }
+
+    /// Statut indiqué par les balises du titre d’un fil sur le forum (`[Vote]`…), discussion à défaut.
+    pub fn statut_depuis_titre(titre: &str) -> Status {
+        static REGEX_BALISES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r##"\s*\[([^\[]*)]"##).unwrap());
+        let mut status = Status::Discussion;
+        for balise in REGEX_BALISES.captures_iter(titre) {
+            let balise = balise.extract::<1>().0.trim().to_lowercase();
+            if balise.contains("vote") {
+                status = Status::Vote;
+            } else if balise.contains("terminé") {
+                status = Status::Vote;
+            } else if balise.contains("développement") {
+                status = Status::EnDev;
+            }
         }
+        status
+    }
+
+    /// Nom d’un fil débarrassé des balises et guillemets de son titre sur le forum.
+    pub fn nom_depuis_titre(titre: &str) -> Option<String> {
+        static REGEX_TITRES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r##"(?i)\s*(?:\s*[\[(][^\[]*?[])][\s/\\\-]*)*[\s:\-"]*([^"]*?(?:"[^"]+"?[^"]*?)*)[\s".]*[\s".]*$"##).unwrap());
+        REGEX_TITRES.captures(titre).and_then(|captures| captures.get(1)).map(|nom| nom.as_str().to_string())
     }
 
     pub fn find_id(url: &String) -> Option<u64> {
@@ -59,8 +194,15 @@ impl Object for Fil {
             pole: Pole::Autre,
             status: Status::Inconnu,
             last_update: Timestamp::now(),
+            creation: None,
+            echeance: None,
+            responsables: Vec::new(),
+            post: None,
+            reponses: None,
             id: 0,
-            modified: false
+            modified: false,
+            historique: Vec::new(),
+            notes: Vec::new()
         }
     }
 
@@ -69,27 +211,57 @@ impl Object for Fil {
     }
 
     fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
-        let lien = data["lien"].as_str().ok_or(ErrType::YamlParseError("Erreur de yaml dans un champ lien.".to_string()))?.to_string();
+        let data = &schema::migrer(data)?;
+        let manquant = |champ: &str| ErrType::YamlParseError(format!("Erreur de yaml dans un champ {champ}."));
+        let texte = |champ: &str| data[champ].as_str().ok_or(manquant(champ));
+        /* Les champs présents depuis la version 1 sont indispensables : une base corrompue ne doit pas être
+         * chargée puis enregistrée par-dessus l’originale. Seuls les champs facultatifs ont une valeur par défaut. */
+        let lien = texte("lien")?.to_string();
         Ok(Self {
-            name: data["nom"].as_str().ok_or(ErrType::YamlParseError("Erreur de yaml dans un champ nom.".to_string()))?.to_string(),
-            status: Status::from(Status::from_str(data["status"].as_str().ok_or(ErrType::YamlParseError("Erreur de yaml dans un status.".to_string()))?)?),
-            pole: Pole::from(Pole::from_str(data["pole"].as_str().ok_or(ErrType::YamlParseError("Erreur de yaml dans un status.".to_string()))?)?),
-            last_update: Timestamp::from_unix_timestamp(data["lastUpdate"].as_i64()
-                .ok_or(ErrType::YamlParseError("Erreur de yaml dans un last_update.".to_string()))?.try_into()?)?,
+            name: texte("nom")?.to_string(),
+            status: Status::from_str(texte("status")?)?,
+            pole: Pole::from_str(texte("pole")?)?,
+            last_update: Timestamp::from_unix_timestamp(data["lastUpdate"].as_i64().ok_or(manquant("lastUpdate"))?)?,
+            creation: data["creation"].as_i64().map(Timestamp::from_unix_timestamp).transpose()?,
+            echeance: data["echeance"].as_i64().map(Timestamp::from_unix_timestamp).transpose()?,
+            responsables: data["responsables"].as_vec().map(|responsables| responsables.iter()
+                .filter_map(|responsable| responsable.as_str().map(str::to_string)).collect()).unwrap_or_default(),
+            post: data["post"].as_i64().and_then(|post| post.try_into().ok()),
+            reponses: data["reponses"].as_i64().and_then(|reponses| reponses.try_into().ok()),
             id: Self::find_id(&lien).ok_or(ErrType::NoneError)?,
-            modified: false,
+            modified: data["edited"].as_bool().unwrap_or(false),
+            historique: data["historique"].as_vec().map(|historique| historique.iter()
+                .map(Transition::from_yaml).collect::<Result<Vec<Transition>, ErrType>>()).transpose()?.unwrap_or_default(),
+            notes: data["notes"].as_vec().map(|notes| notes.iter()
+                .map(Note::from_yaml).collect::<Result<Vec<Note>, ErrType>>()).transpose()?.unwrap_or_default(),
             lien
         })
     }
 
     fn serialize(&self) -> Yaml {
         let mut yaml_out = yaml::Hash::new();
+        yaml_out.insert(Yaml::String("version".to_string()), Yaml::Integer(schema::VERSION));
         yaml_out.insert(Yaml::String("nom".to_string()), Yaml::String(self.name.clone()));
         yaml_out.insert(Yaml::String("lien".to_string()), Yaml::String(self.lien.clone()));
         yaml_out.insert(Yaml::String("pole".to_string()), Yaml::String(self.pole.to_string()));
         yaml_out.insert(Yaml::String("status".to_string()), Yaml::String(self.status.to_string()));
         yaml_out.insert(Yaml::String("lastUpdate".to_string()), Yaml::Integer(self.last_update.timestamp()));
         yaml_out.insert(Yaml::String("edited".to_string()), Yaml::Boolean(self.modified.clone()));
+        if let Some(creation) = &self.creation {
+            yaml_out.insert(Yaml::String("creation".to_string()), Yaml::Integer(creation.unix_timestamp()));
+        }
+        if let Some(echeance) = &self.echeance {
+            yaml_out.insert(Yaml::String("echeance".to_string()), Yaml::Integer(echeance.unix_timestamp()));
+        }
+        yaml_out.insert(Yaml::String("responsables".to_string()), Yaml::Array(self.responsables.iter().cloned().map(Yaml::String).collect()));
+        if let Some(post) = self.post {
+            yaml_out.insert(Yaml::String("post".to_string()), Yaml::Integer(post as i64));
+        }
+        if let Some(reponses) = self.reponses {
+            yaml_out.insert(Yaml::String("reponses".to_string()), Yaml::Integer(reponses as i64));
+        }
+        yaml_out.insert(Yaml::String("historique".to_string()), Yaml::Array(self.historique.iter().map(Transition::serialize).collect()));
+        yaml_out.insert(Yaml::String("notes".to_string()), Yaml::Array(self.notes.iter().map(Note::serialize).collect()));
         Yaml::Hash(yaml_out)
     }
 
@@ -102,12 +274,39 @@ impl Object for Fil {
     }
 
     fn get_embed(&self) -> CreateEmbed {
-        let fields = vec![
-            ("Pôle", self.pole.to_string(), false),
-            ("Statut", self.status.to_string(), false),
+        let relatif = |date: &Timestamp| format!("<t:{}:R>", date.unix_timestamp());
+        let mut fields = vec![
+            ("Pôle", format!("{} {}", self.pole.get_emoji(), self.pole), true),
+            ("Statut", format!("{} {}", self.status.get_badge(), self.status), true)
         ];
+        if let (Status::Vote, Some(echeance)) = (&self.status, &self.echeance) {
+            fields.push(("Fin du vote", format!("<t:{0}:f> (<t:{0}:R>)", echeance.unix_timestamp()), true));
+        }
+        if let Some(creation) = &self.creation {
+            fields.push(("Ouvert", relatif(creation), true));
+        }
+        fields.push(("Mis à jour", relatif(&self.last_update), true));
+        if let Some(reponses) = self.reponses {
+            fields.push(("Réponses", match reponses {
+                0 => "aucune".to_string(),
+                reponses => format!("au moins {reponses}")
+            }, true));
+        }
+        if !self.responsables.is_empty() {
+            fields.push(("Responsables", tronquer(self.responsables.join(", ").as_str(), LIMITE_CHAMP), false));
+        }
+        if let Some(note) = self.notes.last() {
+            let derniere = format!("{} ({}) : {}", note.auteur, relatif(&note.date), note.texte);
+            fields.push(("Dernière note", tronquer(derniere.as_str(), LIMITE_NOTE), false));
+        }
+        if let Some(post) = self.post {
+            fields.push(("Discussion Discord", format!("<#{post}>"), false));
+        }
+        if let Some(flux) = self.flux() {
+            fields.push(("Liens", format!("[Fil du forum]({}) · [Flux RSS]({flux})", self.lien), false));
+        }
         CreateEmbed::new()
-            .title(self.name.clone())
+            .title(tronquer(format!("{} {}", self.pole.get_emoji(), self.name).as_str(), LIMITE_TITRE))
             .url(self.lien.clone())
             .fields(fields)
             .footer(CreateEmbedFooter::new(self.id.to_string()))
@@ -120,7 +319,8 @@ impl Object for Fil {
         let termine = CreateButton::new(format!("f-{id}-t")).style(ButtonStyle::Success).label("Terminé");
         let vote = CreateButton::new(format!("f-{id}-v")).style(ButtonStyle::Secondary).label("Passage au vote");
         let dev = CreateButton::new(format!("f-{id}-d")).style(ButtonStyle::Secondary).label("Passage en développement");
-        let no = CreateButton::new(format!("f-{id}-0")).style(ButtonStyle::Primary).label("Aucune action possible").disabled(true);
+        let pole = CreateButton::new(format!("p-{id}")).style(ButtonStyle::Secondary).label("Pôle");
+        let editer = CreateButton::new(format!("e-{id}")).style(ButtonStyle::Secondary).label("Éditer");
         let mut buttons = Vec::new();
 
         match self.status {
@@ -137,10 +337,10 @@ impl Object for Fil {
                 buttons.push(vote.clone());
                 buttons.push(termine.clone().style(ButtonStyle::Danger));
             }
-            Status::Termine | Status::Inconnu => {
-                buttons.push(no.clone());
-            }
+            Status::Termine | Status::Inconnu => {}
         }
+        buttons.push(pole);
+        buttons.push(editer);
         CreateActionRow::Buttons(buttons)
     }
 
@@ -169,6 +369,7 @@ impl Object for Fil {
     }
 
     async fn buttons(ctx: &SerenityContext, interaction: &mut ComponentInteraction, bot: &mut Bot<Self>) -> Result<(), ErrType> {
+        passerelle::partager(ctx);
         let parts: Vec<&str> = interaction.data.custom_id.split("-").collect();
         let button_type = *parts.get(0)
             .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?;
@@ -181,30 +382,39 @@ impl Object for Fil {
                     "t" | "v" | "d" => {
                         interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
                         if bot.database.contains_key(&id) {
+                            let modification = audit::Modification::commencer(&bot.database, [id], interaction.user.name.as_str());
                             bot.archive(vec![id]);
-                            bot.database.get_mut(&id).unwrap().status = match action {
+                            bot.database.get_mut(&id).unwrap().set_status(match action {
                                 "t" => Status::Termine,
                                 "v" => Status::Vote,
                                 "d" => Status::EnDev,
                                 _ => panic!() /* Impossible */
-                            };
+                            }, Some(interaction.user.name.clone()));
                             bot.database.get_mut(&id).unwrap().modified = true;
+                            modification.terminer(&bot.database);
                         } else {
                             return Err(ErrType::ObjectNotFound(id.to_string()));
                         }
                     }
                     _ => {
                         interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
-                        eprintln!("Action inconnue pressée sur un bouton: f-{id}-{action}");
+                        tracing::warn!(fil = id, action, utilisateur = interaction.user.name.as_str(), "Action inconnue pressée sur un bouton");
                     }
                 }
                 let fil = bot.database.get(&id);
                 interaction.message.edit(ctx, EditMessage::new().embed(
                     fil.ok_or(ErrType::ObjectNotFound(id.to_string()))?.get_embed()
                 ).components(vec![fil.unwrap().get_buttons()])).await?;
-                bot.update_affichans(ctx).await?;
-                bot.save()?;
+                entete::actualiser(ctx, bot).await?;
+                stockage::enregistrer(bot)?;
             }
+            "e" | "p" => {
+                let id: u64 = parts.get(1)
+                    .ok_or(ErrType::InteractionIDError(interaction.data.custom_id.clone(), interaction.message.id.get()))?.parse()?;
+                edition::repondre(ctx, interaction, bot, id, button_type).await?;
+            }
+            "r" => entete::rafraichir(ctx, interaction).await?,
+            "c" => { /* Boutons de confirmation, gérés par la commande qui les a envoyés. */ }
             _ => { interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?; }
         }
 
@@ -212,70 +422,48 @@ impl Object for Fil {
     }
 
     async fn maj_rss(bot: &DataType<Self>) -> Result<(), ErrType> {
-        let mut last_date = DateTime::from_timestamp(0, 0).unwrap();
-        let bot = &mut bot.lock().await;
-        for (pole, url) in [
-            (Pole::Interne, "http://commandemento5.wikidot.com/feed/forum/ct-6827498.xml"),
-            (Pole::Ambassade, "http://commandemento5.wikidot.com/feed/forum/ct-1905805.xml"),
-            (Pole::RetD, "http://commandemento5.wikidot.com/feed/forum/ct-6827479.xml"),
-            (Pole::Technique, "http://commandemento5.wikidot.com/feed/forum/ct-6827478.xml"),
-            (Pole::Evenementiel, "http://commandemento5.wikidot.com/feed/forum/ct-6827484.xml"),
-            (Pole::Creation, "http://commandemento5.wikidot.com/feed/forum/ct-7643353.xml"),
-            (Pole::Legal, "http://commandemento5.wikidot.com/feed/forum/ct-1905799.xml")] {
-
-            let regex_balises = Regex::new(r##"\s*\[([^\[]*)]"##).unwrap();
-            let regex_titres = Regex::new(r##"(?i)\s*(?:\s*[\[(][^\[]*?[])][\s/\\\-]*)*[\s:\-"]*([^"]*?(?:"[^"]+"?[^"]*?)*)[\s".]*[\s".]*$"##).unwrap();
-            let rss = Channel::read_from(&reqwest::get(url).await?.bytes().await?[..])?;
-            for entry in &rss.items {
-                let date = try_loop!(DateTime::parse_from_rfc2822(entry.pub_date.as_ref().unwrap().as_str()), "Erreur lors de la récupération des flux RSS: pas de date.").to_utc();
-                if date > bot.last_rss_update {
-                    if entry.title.as_ref().is_some_and(|str| { str.contains("]") }) {
-                        let mut status = Status::Discussion;
-                        for balise in regex_balises.captures_iter(entry.title.as_ref().unwrap()) {
-                            let balise = balise.extract::<1>().0.trim().to_lowercase();
-                            if balise.contains("vote") {
-                                status = Status::Vote;
-                            } else if balise.contains("terminé") {
-                                status = Status::Vote;
-                            } else if balise.contains("développement") {
-                                status = Status::EnDev;
-                            }
-                        }
-                        let mut title = try_loop!(try_loop!(regex_titres.captures(entry.title.as_ref().unwrap())
-                                .ok_or(ErrType::NoneError), "Erreur lors de l’interprétation du titre.").extract::<1>().1.get(0)
-                                .ok_or(ErrType::NoneError), "Erreur lors de l’interprétation du titre.").to_string();
-                        if title.is_empty() {
-                            title = format!("(sans nom {})", bot.search("sans nom").len());
-                        }
+        /* fondabots-lib appelle cette méthode à sa propre fréquence : l’intervalle configuré s’y ajoute. */
+        stockage::partager(bot);
+        releve::planifie(bot).await;
+        Ok(())
+    }
+}
 
-                        let lien = try_loop!(entry.link.clone().ok_or(ErrType::NoneError), "Pas de lien dans une entrée RSS.");
-                        let id: u64 = try_loop!(Fil::find_id(&lien).ok_or(ErrType::NoneError), "Lien mal formé dans une entrée RSS.");
-
-                        let fil = Fil {
-                            status,
-                            pole: pole.clone(),
-                            name: title,
-                            lien,
-                            last_update: Timestamp::now(),
-                            modified: false,
-                            id,
-                        };
-                        if !bot.database.contains_key(&id) {
-                            bot.database.insert(id, fil);
-                        } else {
-                            eprintln!("Ajout RSS d’un écrit déjà ajouté. Informations : écrit [{}] - last_rss_update [{}] - last_date [{}] - date>last_rss_update [{}]", date, bot.last_rss_update, last_date, date > bot.last_rss_update);
-                        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                    }
-                }
-                if date > last_date {
-                    last_date = date;
-                }
-            }
-        }
+    fn champs(embed: &serde_json::Value) -> Vec<(String, String)> {
+        embed["fields"].as_array().unwrap().iter()
+            .map(|champ| (champ["name"].as_str().unwrap().to_string(), champ["value"].as_str().unwrap().to_string())).collect()
+    }
 
-        bot.last_rss_update = last_date;
-        bot.update_affichans = true;
-        Ok(())
+    #[test]
+    fn embed_adapte_et_borne() {
+        let mut fil = Fil::new("Test".to_string(), "http://commandemento5.wikidot.com/forum/t-42/test".to_string(),
+                               Pole::Technique, Status::Discussion);
+        fil.creation = None;
+        let embed = serde_json::to_value(fil.get_embed()).unwrap();
+        let noms: Vec<String> = champs(&embed).into_iter().map(|(nom, _)| nom).collect();
+        assert_eq!(noms, vec!["Pôle", "Statut", "Mis à jour", "Liens"]);
+        assert!(champs(&embed)[3].1.contains("http://commandemento5.wikidot.com/feed/forum/t-42.xml"));
+
+        /* Textes démesurés : chaque partie de l’embed reste sous les limites de Discord. */
+        fil.set_name("n".repeat(400));
+        fil.reponses = Some(3);
+        for i in 0..200 {
+            fil.assigner(format!("responsable{i}"));
+        }
+        fil.ajouter_note("a".repeat(500), "x".repeat(3000));
+        let embed = serde_json::to_value(fil.get_embed()).unwrap();
+        let titre = embed["title"].as_str().unwrap();
+        assert_eq!(titre.chars().count(), LIMITE_TITRE);
+        let champs = champs(&embed);
+        assert!(champs.iter().any(|(nom, valeur)| nom == "Réponses" && valeur == "au moins 3"));
+        /* La note est bornée auteur compris. */
+        assert!(champs.iter().any(|(nom, valeur)| nom == "Dernière note" && valeur.chars().count() == LIMITE_NOTE));
+        assert!(champs.iter().all(|(_, valeur)| valeur.chars().count() <= LIMITE_CHAMP));
+        let total = titre.chars().count() + champs.iter().map(|(nom, valeur)| nom.chars().count() + valeur.chars().count()).sum::<usize>();
+        assert!(total <= 6000);
     }
-}
\ No newline at end of file
+}
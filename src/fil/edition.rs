@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use poise::Modal;
+use poise::serenity_prelude as serenity;
+use serenity::all::{ComponentInteraction, ComponentInteractionCollector, ComponentInteractionDataKind, CreateActionRow, CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMessage, Member, Message, ModalInteractionCollector};
+use serenity::Context as SerenityContext;
+use strum::IntoEnumIterator;
+
+use fondabots_lib::{Bot, ErrType, Object};
+
+use super::fields::Pole;
+use super::Fil;
+use crate::{audit, config, entete, flux, stockage};
+
+/// Délai laissé pour remplir le formulaire ou choisir le pôle.
+const DELAI: Duration = Duration::from_secs(600);
+
+/// Formulaire de modification d’un fil, ouvert par le bouton « Éditer ».
+#[derive(Debug, Modal)]
+#[name = "Modifier le fil"]
+struct Modification {
+    #[name = "Nom du fil"]
+    #[max_length = 200]
+    nom: String,
+    #[name = "Lien forum du fil"]
+    lien: String,
+    #[name = "Ajouter une note (facultatif)"]
+    #[paragraph]
+    note: Option<String>
+}
+
+/// Vérifie que le membre a l’un des rôles autorisés à modifier les fils.
+pub fn autorise(membre: Option<&Member>) -> bool {
+    let roles = &config::get().edition.roles;
+    roles.is_empty() || membre.is_some_and(|membre| membre.roles.iter().any(|role| roles.contains(&role.get())))
+}
+
+fn reponse_privee(texte: impl Into<String>) -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(texte).ephemeral(true))
+}
+
+/// Répond aux boutons « Éditer » (`e`) et « Pôle » (`p`) d’un fil. Le formulaire ou le menu est
+/// attendu en tâche de fond, pour ne pas bloquer la base de données pendant la saisie.
+pub async fn repondre(ctx: &SerenityContext, interaction: &ComponentInteraction, bot: &Bot<Fil>, id: u64, action: &str) -> Result<(), ErrType> {
+    if !autorise(interaction.member.as_ref()) {
+        interaction.create_response(ctx, reponse_privee("Vous n’avez pas la permission de modifier les fils.")).await?;
+        return Ok(());
+    }
+    let fil = bot.database.get(&id).ok_or(ErrType::ObjectNotFound(id.to_string()))?;
+    /* Le préfixe « c » est ignoré par Fil::buttons, la réponse est gérée ici. */
+    let custom_id = format!("c-{}-{action}", interaction.id);
+    let message = (*interaction.message).clone();
+    let auteur = interaction.user.name.clone();
+    if action == "e" {
+        let defaut = Modification { nom: fil.name.clone(), lien: fil.lien.clone(), note: None };
+        interaction.create_response(ctx, Modification::create(Some(defaut), custom_id.clone())).await?;
+        tokio::spawn(attendre_modification(ctx.clone(), custom_id, id, message, auteur));
+    } else {
+        let options = Pole::iter().map(|pole| CreateSelectMenuOption::new(pole.to_string(), flux::identifiant(&pole))
+            .default_selection(pole == fil.pole)).collect();
+        let menu = CreateSelectMenu::new(custom_id.clone(), CreateSelectMenuKind::String { options }).placeholder("Nouveau pôle");
+        interaction.create_response(ctx, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+            .content(format!("Pôle du fil « {} » :", fil.name))
+            .components(vec![CreateActionRow::SelectMenu(menu)])
+            .ephemeral(true))).await?;
+        tokio::spawn(attendre_pole(ctx.clone(), custom_id, id, message, auteur));
+    }
+    Ok(())
+}
+
+/// Applique une modification au fil, avec audit et enregistrement, puis rafraîchit son embed.
+/// La modification archive le fil (pour `/annuler`) une fois vérifiée, avant de le changer.
+async fn modifier(ctx: &SerenityContext, id: u64, message: &mut Message, auteur: &str,
+                  modification: impl FnOnce(&mut Bot<Fil>) -> Result<String, String>) -> String {
+    let Some(bot) = stockage::bot() else {
+        return "La base de données n’est pas encore chargée.".to_string();
+    };
+    let bot = &mut bot.lock().await;
+    if !bot.database.contains_key(&id) {
+        return format!("Le fil {id} n’existe plus.");
+    }
+    let audit = audit::Modification::commencer(&bot.database, [id], auteur);
+    let texte = match modification(bot) {
+        Ok(resultat) => resultat,
+        Err(e) => return e
+    };
+    audit.terminer(&bot.database);
+    if let Some(fil) = bot.database.get(&id) {
+        if let Err(e) = message.edit(ctx, EditMessage::new().embed(fil.get_embed()).components(vec![fil.get_buttons()])).await {
+            tracing::warn!(erreur = %e, fil = id, "Embed du fil modifié non rafraîchi");
+        }
+    }
+    if let Err(e) = entete::actualiser(ctx, bot).await {
+        tracing::error!(erreur = %e, "Erreur lors de la mise à jour des affichans");
+    }
+    if let Err(e) = stockage::enregistrer(bot) {
+        tracing::error!(erreur = %e, fil = id, "Erreur lors de l’enregistrement de la base de données");
+    }
+    texte
+}
+
+/// Vérifie que le nouveau lien d’un fil mène toujours au même fil.
+fn verifier_lien(id: u64, lien: &String) -> Result<(), String> {
+    let nouvel_id = Fil::find_id(lien).ok_or("Lien malformé, impossible de déterminer l’identifiant du fil.".to_string())?;
+    /* L’identifiant sert de clé aux archives et aux abonnements : un lien vers un autre fil
+     * en ferait un nouveau fil, à ajouter comme tel. */
+    if nouvel_id != id {
+        return Err(format!("Ce lien mène à un autre fil ({nouvel_id}) : supprimez celui-ci avec `/supprimer` puis ajoutez l’autre avec `/ajouter`."));
+    }
+    Ok(())
+}
+
+async fn attendre_modification(ctx: SerenityContext, custom_id: String, id: u64, mut message: Message, auteur: String) {
+    let Some(reponse) = ModalInteractionCollector::new(&ctx).custom_ids(vec![custom_id]).timeout(DELAI).await else {
+        return;
+    };
+    let texte = match Modification::parse(reponse.data.clone()) {
+        Ok(saisie) => modifier(&ctx, id, &mut message, auteur.as_str(), |bot| {
+            verifier_lien(id, &saisie.lien)?;
+            bot.archive(vec![id]);
+            let fil = bot.database.get_mut(&id).unwrap();
+            fil.name = saisie.nom.trim().to_string();
+            fil.lien = saisie.lien.trim().to_string();
+            if let Some(note) = saisie.note.filter(|note| !note.trim().is_empty()) {
+                fil.ajouter_note(auteur.clone(), note);
+            }
+            fil.modified = true;
+            Ok(format!("Fil « {} » modifié.", fil.name))
+        }).await,
+        Err(e) => e.to_string()
+    };
+    if let Err(e) = reponse.create_response(&ctx, reponse_privee(texte)).await {
+        tracing::warn!(erreur = %e, fil = id, "Réponse au formulaire de modification non envoyée");
+    }
+}
+
+async fn attendre_pole(ctx: SerenityContext, custom_id: String, id: u64, mut message: Message, auteur: String) {
+    let Some(reponse) = ComponentInteractionCollector::new(&ctx).custom_ids(vec![custom_id]).timeout(DELAI).await else {
+        return;
+    };
+    let pole = match &reponse.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values.first().and_then(|valeur| flux::pole_depuis_identifiant(valeur)),
+        _ => None
+    };
+    let texte = match pole {
+        Some(pole) => modifier(&ctx, id, &mut message, auteur.as_str(), |bot| {
+            bot.archive(vec![id]);
+            let fil = bot.database.get_mut(&id).unwrap();
+            fil.pole = pole;
+            fil.modified = true;
+            Ok(format!("Fil « {} » déplacé dans le pôle {}.", fil.name, fil.pole))
+        }).await,
+        None => "Pôle inconnu.".to_string()
+    };
+    let mise_a_jour = CreateInteractionResponseMessage::new().content(texte).components(vec![]);
+    if let Err(e) = reponse.create_response(&ctx, CreateInteractionResponse::UpdateMessage(mise_a_jour)).await {
+        tracing::warn!(erreur = %e, fil = id, "Réponse au choix du pôle non envoyée");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuse_un_lien_vers_un_autre_fil() {
+        assert!(verifier_lien(1234567, &"http://commandemento5.wikidot.com/forum/t-1234567/nouveau-titre".to_string()).is_ok());
+        let erreur = verifier_lien(1234567, &"http://commandemento5.wikidot.com/forum/t-7654321/charte".to_string()).unwrap_err();
+        assert!(erreur.contains("7654321"));
+        assert!(verifier_lien(1234567, &"http://commandemento5.wikidot.com/forum/".to_string()).is_err());
+    }
+}
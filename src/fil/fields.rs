@@ -31,18 +31,33 @@ pub enum Pole {
 impl Pole {
     pub fn get_color(&self) -> i32 {
         match self {
-            Pole::Disciplinaire => 0xFFFFFF,
-            Pole::Ambassade => 0xFFFFFF,
-            Pole::RetD => 0xFFFFFF,
-            Pole::Technique => 0xFFFFFF,
-            Pole::Evenementiel => 0xFFFFFF,
-            Pole::Creation => 0xFFFFFF,
-            Pole::Traduction => 0xFFFFFF,
-            Pole::Legal => 0xFFFFFF,
+            Pole::Disciplinaire => 0xE67E22,
+            Pole::Ambassade => 0x3498DB,
+            Pole::RetD => 0x9B59B6,
+            Pole::Technique => 0x607D8B,
+            Pole::Evenementiel => 0xF1C40F,
+            Pole::Creation => 0xE91E63,
+            Pole::Traduction => 0x1ABC9C,
+            Pole::Legal => 0x2C3E50,
             Pole::Interne => 0xFF0000,
             Pole::Autre => 0xFFFFFF
         }
     }
+
+    pub fn get_emoji(&self) -> &'static str {
+        match self {
+            Pole::Disciplinaire => "🛡️",
+            Pole::Ambassade => "🤝",
+            Pole::RetD => "🔬",
+            Pole::Technique => "🛠️",
+            Pole::Evenementiel => "🎉",
+            Pole::Creation => "🎨",
+            Pole::Traduction => "🌐",
+            Pole::Legal => "⚖️",
+            Pole::Interne => "🔒",
+            Pole::Autre => "📁"
+        }
+    }
 }
 
 impl Display for Pole {
@@ -93,6 +108,19 @@ pub enum Status {
     Inconnu
 }
 
+impl Status {
+    /// Pastille affichée devant le statut dans les embeds.
+    pub fn get_badge(&self) -> &'static str {
+        match self {
+            Status::Discussion => "💬",
+            Status::Vote => "🗳️",
+            Status::EnDev => "🚧",
+            Status::Termine => "✅",
+            Status::Inconnu => "❔"
+        }
+    }
+}
+
 impl Display for Status {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name())
@@ -122,7 +150,7 @@ impl Field<Fil> for Status {
     }
 
     fn set_for(obj: &mut Fil, field: &Self) {
-        obj.status = field.clone();
+        obj.set_status(field.clone(), None);
     }
 
     fn field_name() -> &'static str {
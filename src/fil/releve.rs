@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rss::Channel;
+use serenity::all::Timestamp;
+
+use fondabots_lib::{DataType, ErrType};
+
+use super::fields::Pole;
+use super::{Fil, CATEGORIES};
+use crate::{audit, config, entete, essayer, stockage};
+
+/// Nombre de fils ajoutés et mis à jour par le relevé d’un flux.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Compte {
+    pub ajoutes: usize,
+    pub mis_a_jour: usize
+}
+
+/// Dernier relevé d’un flux : date, durée et résultat (ou erreur).
+#[derive(Clone, Debug)]
+pub struct Etat {
+    pub date: Timestamp,
+    pub duree: Duration,
+    pub resultat: Result<Compte, String>
+}
+
+/// Dernier relevé de chaque flux, dans l’ordre des catégories.
+static ETATS: Mutex<Vec<(Pole, Etat)>> = Mutex::new(Vec::new());
+
+/// Fin du dernier relevé, pour respecter l’intervalle configuré.
+static DERNIER: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Dernier relevé demandé à la main, pour le délai entre deux demandes.
+static DERNIERE_DEMANDE: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Les relevés passent l’un après l’autre.
+static RELEVE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+pub fn etats() -> Vec<(Pole, Etat)> {
+    ETATS.lock().unwrap().clone()
+}
+
+/// Temps écoulé depuis la fin du dernier relevé.
+pub fn depuis_dernier() -> Option<Duration> {
+    DERNIER.lock().unwrap().map(|dernier| dernier.elapsed())
+}
+
+/// Enregistre une demande de relevé manuel, ou renvoie le temps à attendre avant la prochaine.
+pub fn demander() -> Result<(), Duration> {
+    let delai = Duration::from_secs(config::get().releve.delai);
+    let mut derniere = DERNIERE_DEMANDE.lock().unwrap();
+    if let Some(restant) = derniere.and_then(|derniere| delai.checked_sub(derniere.elapsed())) {
+        return Err(restant);
+    }
+    *derniere = Some(Instant::now());
+    Ok(())
+}
+
+/// Intègre à la base les entrées d’un flux postérieures à `depuis` : les fils inconnus sont ajoutés,
+/// les fils connus dont l’entrée est plus récente que leur dernière mise à jour sont mis à jour.
+/// Renvoie aussi la date de l’entrée la plus récente.
+fn integrer(database: &mut HashMap<u64, Fil>, depuis: DateTime<Utc>, pole: &Pole, rss: &Channel, url: &str) -> (Compte, Option<DateTime<Utc>>) {
+    let mut compte = Compte::default();
+    let mut plus_recente = None;
+    for entry in &rss.items {
+        let date = essayer!(DateTime::parse_from_rfc2822(entry.pub_date.as_deref().unwrap_or_default()),
+            flux = url, entree = entry.link.as_deref(), "Date absente ou mal formée dans une entrée RSS").to_utc();
+        if plus_recente.is_none_or(|plus_recente| date > plus_recente) {
+            plus_recente = Some(date);
+        }
+        if date <= depuis || !entry.title.as_ref().is_some_and(|str| { str.contains("]") }) {
+            continue;
+        }
+        let status = Fil::statut_depuis_titre(entry.title.as_ref().unwrap());
+        let mut title = essayer!(Fil::nom_depuis_titre(entry.title.as_ref().unwrap()).ok_or(ErrType::NoneError),
+            flux = url, titre = entry.title.as_deref(), "Titre d’une entrée RSS non interprétable");
+        if title.is_empty() {
+            title = format!("(sans nom {})", database.values().filter(|fil| fil.name.contains("sans nom")).count());
+        }
+
+        let lien = essayer!(entry.link.clone().ok_or(ErrType::NoneError),
+            flux = url, titre = entry.title.as_deref(), "Pas de lien dans une entrée RSS");
+        let id: u64 = essayer!(Fil::find_id(&lien).ok_or(ErrType::NoneError),
+            flux = url, lien = lien.as_str(), "Lien mal formé dans une entrée RSS");
+        let date_entree = essayer!(Timestamp::from_unix_timestamp(date.timestamp()),
+            flux = url, lien = lien.as_str(), "Date d’une entrée RSS hors limites");
+
+        match database.get_mut(&id) {
+            Some(fil) => if fil.last_update.unix_timestamp() < date.timestamp() {
+                fil.last_update = date_entree;
+                fil.modified = true;
+                compte.mis_a_jour += 1;
+            },
+            None => {
+                database.insert(id, Fil {
+                    status,
+                    pole: pole.clone(),
+                    name: title,
+                    lien,
+                    last_update: Timestamp::now(),
+                    creation: Some(date_entree),
+                    echeance: None,
+                    responsables: Vec::new(),
+                    post: None,
+                    reponses: None,
+                    modified: false,
+                    id,
+                    historique: Vec::new(),
+                    notes: Vec::new()
+                });
+                compte.ajoutes += 1;
+            }
+        }
+    }
+    (compte, plus_recente)
+}
+
+async fn lire(url: &str) -> Result<Channel, ErrType> {
+    Ok(Channel::read_from(&reqwest::get(url).await?.bytes().await?[..])?)
+}
+
+/// Relève les flux RSS de toutes les catégories du forum et renvoie le résultat de chacun.
+///
+/// Un flux inaccessible n’empêche pas de relever les autres ; la date du dernier relevé de la base
+/// n’avance alors pas, pour que ses entrées soient reprises la fois suivante.
+pub async fn relever(bot: &DataType<Fil>) -> Vec<(Pole, Result<Compte, String>)> {
+    let _verrou = RELEVE.lock().await;
+    let bot = &mut bot.lock().await;
+    let connus: HashSet<u64> = bot.database.keys().copied().collect();
+    let mut last_date = DateTime::from_timestamp(0, 0).unwrap();
+    let mut complet = true;
+    let mut etats = Vec::new();
+    for (pole, categorie) in CATEGORIES {
+        let url = format!("http://commandemento5.wikidot.com/feed/forum/ct-{categorie}.xml");
+        let url = url.as_str();
+        let debut = Instant::now();
+        let resultat = match lire(url).await {
+            Ok(rss) => {
+                tracing::debug!(flux = url, entrees = rss.items.len(), "Flux RSS récupéré");
+                let depuis = bot.last_rss_update;
+                let (compte, plus_recente) = integrer(&mut bot.database, depuis, &pole, &rss, url);
+                last_date = last_date.max(plus_recente.unwrap_or(last_date));
+                Ok(compte)
+            }
+            Err(e) => {
+                tracing::error!(flux = url, erreur = %e, "Flux RSS inaccessible ou illisible");
+                complet = false;
+                Err(e.to_string())
+            }
+        };
+        etats.push((pole, Etat { date: Timestamp::now(), duree: debut.elapsed(), resultat }));
+    }
+
+    let ajoutes: Vec<u64> = bot.database.keys().filter(|id| !connus.contains(id)).copied().collect();
+    audit::Modification::ajouts(ajoutes, "Flux RSS").terminer(&bot.database);
+    if complet {
+        bot.last_rss_update = last_date;
+    }
+    entete::signaler(bot);
+    /* fondabots-lib n’enregistre la base qu’en YAML : les fils relevés doivent aussi l’être dans la base SQLite. */
+    if let Err(e) = stockage::enregistrer(bot) {
+        tracing::error!(erreur = %e, "Erreur lors de l’enregistrement de la base de données");
+    }
+    *DERNIER.lock().unwrap() = Some(Instant::now());
+    let rapport = etats.iter().map(|(pole, etat): &(Pole, Etat)| (pole.clone(), etat.resultat.clone())).collect();
+    *ETATS.lock().unwrap() = etats;
+    rapport
+}
+
+/// Relève les flux si l’intervalle configuré s’est écoulé depuis le dernier relevé.
+pub async fn planifie(bot: &DataType<Fil>) {
+    let intervalle = Duration::from_secs(config::get().releve.intervalle * 60);
+    if depuis_dernier().is_none_or(|depuis| depuis >= intervalle) {
+        relever(bot).await;
+    }
+}
+
+/// Relève les flux à l’intervalle configuré, indépendamment de la fréquence de fondabots-lib.
+pub async fn planifier() {
+    let bot = stockage::attendre().await;
+    loop {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        planifie(&bot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::fil::fields::Status;
+
+    #[test]
+    fn ajoute_et_met_a_jour() {
+        let rss = Channel::read_from(&fs::read(stockage::tests::chemin_fixture("categorie.xml")).unwrap()[..]).unwrap();
+        let mut database: HashMap<u64, Fil> = stockage::tests::fixture("v2.yml").into_iter().map(|fil| (fil.id, fil)).collect();
+        let depuis = DateTime::parse_from_rfc3339("2024-07-01T00:00:00Z").unwrap().to_utc();
+
+        let (compte, plus_recente) = integrer(&mut database, depuis, &Pole::Technique, &rss, "categorie.xml");
+        /* Un nouveau fil au vote ; la refonte du guide, déjà suivie, a une entrée plus récente ; l’entrée
+         * antérieure au dernier relevé est ignorée. */
+        assert_eq!(compte, Compte { ajoutes: 1, mis_a_jour: 1 });
+        assert_eq!(plus_recente.map(|date| date.timestamp()), Some(1722600000));
+        assert_eq!(database[&2000001].name, "Nouvelle charte graphique");
+        assert_eq!(database[&2000001].status, Status::Vote);
+        assert_eq!(database[&1234567].last_update.unix_timestamp(), 1722500000);
+        assert!(!database.contains_key(&2000002));
+
+        /* Un second relevé des mêmes entrées ne change rien. */
+        let (compte, _) = integrer(&mut database, depuis, &Pole::Technique, &rss, "categorie.xml");
+        assert_eq!(compte, Compte::default());
+    }
+}
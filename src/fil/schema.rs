@@ -0,0 +1,137 @@
+use yaml_rust2::{Yaml, yaml};
+
+use fondabots_lib::ErrType;
+
+/// Version du format d’un fil sérialisé, enregistrée dans son champ `version`.
+///
+/// Le fichier de base de données étant écrit par fondabots-lib, chaque fil porte sa propre version.
+/// Un fil sans champ `version` est en version 1.
+///
+/// - 1 : `nom`, `lien`, `pole`, `status`, `lastUpdate`, `edited` ;
+/// - 2 : ajout de `historique` et `notes`.
+///
+/// Les champs facultatifs (`creation`, `echeance`, `responsables`, `post`, `reponses`…) prennent une valeur
+/// par défaut lorsqu’ils sont absents et ne changent pas la version.
+pub const VERSION: i64 = 2;
+
+type Migration = fn(&mut yaml::Hash) -> Result<(), ErrType>;
+
+/// `MIGRATIONS[i]` fait passer un fil de la version `i + 1` à la version `i + 2`.
+const MIGRATIONS: [Migration; (VERSION - 1) as usize] = [
+    v1_vers_v2
+];
+
+fn cle(s: &str) -> Yaml {
+    Yaml::String(s.to_string())
+}
+
+fn v1_vers_v2(data: &mut yaml::Hash) -> Result<(), ErrType> {
+    for champ in ["historique", "notes"] {
+        if !data.contains_key(&cle(champ)) {
+            data.insert(cle(champ), Yaml::Array(Vec::new()));
+        }
+    }
+    Ok(())
+}
+
+/// Met à jour un fil sérialisé dans une version antérieure vers la version courante.
+pub fn migrer(data: &Yaml) -> Result<Yaml, ErrType> {
+    let mut hash = data.as_hash().cloned()
+        .ok_or(ErrType::YamlParseError("Un fil doit être un dictionnaire.".to_string()))?;
+    let version = match hash.get(&cle("version")) {
+        None => 1,
+        Some(Yaml::Integer(version)) if (1..=VERSION).contains(version) => *version,
+        Some(version) => return Err(ErrType::YamlParseError(
+            format!("Version de fil non prise en charge : {version:?} (version courante : {VERSION}).")))
+    };
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(&mut hash)?;
+    }
+    hash.insert(cle("version"), Yaml::Integer(VERSION));
+    Ok(Yaml::Hash(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use fondabots_lib::Object;
+
+    use crate::fil::fields::{Pole, Status};
+    use crate::fil::Fil;
+    use crate::stockage;
+
+    use super::*;
+
+    fn fixture(nom: &str) -> Vec<Fil> {
+        let mut fils = stockage::tests::fixture(nom);
+        fils.sort_by_key(|fil| fil.get_id());
+        fils
+    }
+
+    #[test]
+    fn charge_v1() {
+        let fils = fixture("v1.yml");
+        assert_eq!(fils.len(), 2);
+        assert_eq!(fils[0].get_id(), 1234567);
+        assert_eq!(fils[0].get_name(), "Refonte du guide");
+        assert_eq!(fils[0].pole, Pole::Technique);
+        assert_eq!(fils[0].status, Status::Vote);
+        assert_eq!(fils[0].last_update.timestamp(), 1721000000);
+        assert!(!fils[0].is_modified());
+        assert!(fils[0].historique.is_empty());
+        assert!(fils[0].notes.is_empty());
+        assert!(fils[1].is_modified());
+    }
+
+    #[test]
+    fn charge_v2() {
+        let fils = fixture("v2.yml");
+        assert_eq!(fils.len(), 3);
+        let (regles, guide, charte) = (&fils[0], &fils[1], &fils[2]);
+        assert_eq!(guide.historique.len(), 1);
+        assert_eq!(guide.historique[0].de, Status::Discussion);
+        assert_eq!(guide.historique[0].vers, Status::Vote);
+        assert_eq!(guide.historique[0].auteur.as_deref(), Some("alice"));
+        assert!(guide.historique[0].nom.is_none());
+        assert_eq!(guide.notes.len(), 1);
+        assert_eq!(guide.notes[0].texte, "À relire avant le vote.");
+        assert_eq!(guide.creation.map(|creation| creation.unix_timestamp()), Some(1720000000));
+        assert_eq!(guide.echeance.map(|echeance| echeance.unix_timestamp()), Some(1721600000));
+        assert_eq!(guide.get_responsables(), &vec!["alice".to_string(), "carole".to_string()]);
+        assert_eq!(guide.post, Some(1262000000000000001));
+        assert_eq!(guide.reponses, Some(12));
+        /* Fil écrit avant l’ajout du champ version, mais avec historique et notes. */
+        assert_eq!(charte.historique.len(), 1);
+        assert_eq!(charte.historique[0].nom.as_deref(), Some("Charte de modération"));
+        assert_eq!(charte.historique[0].pole, Some(Pole::Legal));
+        /* Les champs facultatifs absents prennent leur valeur par défaut. */
+        assert!(regles.creation.is_none() && regles.echeance.is_none() && regles.post.is_none() && regles.reponses.is_none());
+        assert!(regles.get_responsables().is_empty());
+    }
+
+    #[test]
+    fn refuse_champs_obligatoires_manquants() {
+        let chemin = format!("{}/tests/fixtures/incomplet.yml", env!("CARGO_MANIFEST_DIR"));
+        assert!(matches!(stockage::charger_yaml(chemin.as_str()), Err(ErrType::YamlParseError(_))));
+    }
+
+    #[test]
+    fn aller_retour() {
+        for fixture_nom in ["v1.yml", "v2.yml"] {
+            for fil in fixture(fixture_nom) {
+                let serialise = fil.serialize();
+                assert_eq!(serialise["version"].as_i64(), Some(VERSION));
+                assert_eq!(Fil::from_yaml(&serialise).unwrap(), fil);
+            }
+        }
+    }
+
+    #[test]
+    fn refuse_version_future() {
+        let mut data = Fil::new("Test".to_string(), "http://commandemento5.wikidot.com/forum/t-1/".to_string(),
+                                Pole::Autre, Status::Discussion).serialize();
+        if let Yaml::Hash(hash) = &mut data {
+            hash.insert(cle("version"), Yaml::Integer(VERSION + 1));
+        }
+        assert!(Fil::from_yaml(&data).is_err());
+    }
+}
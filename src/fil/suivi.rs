@@ -0,0 +1,85 @@
+use std::str::FromStr;
+
+use serenity::all::Timestamp;
+use yaml_rust2::{Yaml, yaml};
+
+use fondabots_lib::ErrType;
+
+use super::fields::{Pole, Status};
+
+/// Changement de statut d’un fil, conservé dans son historique.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Transition {
+    pub de: Status,
+    pub vers: Status,
+    pub date: Timestamp,
+    pub auteur: Option<String>,
+    /// Nom et pôle du fil au moment du changement, inconnus pour les changements enregistrés avant leur introduction.
+    pub nom: Option<String>,
+    pub pole: Option<Pole>
+}
+
+/// Note libre laissée par un membre du staff sur un fil.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Note {
+    pub auteur: String,
+    pub date: Timestamp,
+    pub texte: String
+}
+
+fn champ_str<'a>(data: &'a Yaml, champ: &str) -> Result<&'a str, ErrType> {
+    data[champ].as_str().ok_or(ErrType::YamlParseError(format!("Erreur de yaml dans un champ {champ}.")))
+}
+
+fn champ_date(data: &Yaml, champ: &str) -> Result<Timestamp, ErrType> {
+    Ok(Timestamp::from_unix_timestamp(data[champ].as_i64()
+        .ok_or(ErrType::YamlParseError(format!("Erreur de yaml dans un champ {champ}.")))?)?)
+}
+
+impl Transition {
+    pub fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
+        Ok(Self {
+            de: Status::from_str(champ_str(data, "de")?)?,
+            vers: Status::from_str(champ_str(data, "vers")?)?,
+            date: champ_date(data, "date")?,
+            auteur: data["auteur"].as_str().map(str::to_string),
+            nom: data["nom"].as_str().map(str::to_string),
+            pole: data["pole"].as_str().map(Pole::from_str).transpose()?
+        })
+    }
+
+    pub fn serialize(&self) -> Yaml {
+        let mut yaml_out = yaml::Hash::new();
+        yaml_out.insert(Yaml::String("de".to_string()), Yaml::String(self.de.to_string()));
+        yaml_out.insert(Yaml::String("vers".to_string()), Yaml::String(self.vers.to_string()));
+        yaml_out.insert(Yaml::String("date".to_string()), Yaml::Integer(self.date.timestamp()));
+        if let Some(auteur) = &self.auteur {
+            yaml_out.insert(Yaml::String("auteur".to_string()), Yaml::String(auteur.clone()));
+        }
+        if let Some(nom) = &self.nom {
+            yaml_out.insert(Yaml::String("nom".to_string()), Yaml::String(nom.clone()));
+        }
+        if let Some(pole) = &self.pole {
+            yaml_out.insert(Yaml::String("pole".to_string()), Yaml::String(pole.to_string()));
+        }
+        Yaml::Hash(yaml_out)
+    }
+}
+
+impl Note {
+    pub fn from_yaml(data: &Yaml) -> Result<Self, ErrType> {
+        Ok(Self {
+            auteur: champ_str(data, "auteur")?.to_string(),
+            date: champ_date(data, "date")?,
+            texte: champ_str(data, "texte")?.to_string()
+        })
+    }
+
+    pub fn serialize(&self) -> Yaml {
+        let mut yaml_out = yaml::Hash::new();
+        yaml_out.insert(Yaml::String("auteur".to_string()), Yaml::String(self.auteur.clone()));
+        yaml_out.insert(Yaml::String("date".to_string()), Yaml::Integer(self.date.timestamp()));
+        yaml_out.insert(Yaml::String("texte".to_string()), Yaml::String(self.texte.clone()));
+        Yaml::Hash(yaml_out)
+    }
+}
@@ -0,0 +1,140 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rss::{CategoryBuilder, ChannelBuilder, GuidBuilder, Item, ItemBuilder};
+use strum::IntoEnumIterator;
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::audit::{Action, Evenement};
+use crate::config;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::suivi::Transition;
+use crate::fil::Fil;
+use crate::stockage;
+
+/// Identifiant d’un pôle dans les adresses et noms de fichiers des flux : `technique`, `retd`, `legal`…
+pub fn identifiant(pole: &Pole) -> String {
+    format!("{pole:?}").to_lowercase()
+}
+
+pub fn pole_depuis_identifiant(identifiant: &str) -> Option<Pole> {
+    Pole::iter().find(|pole| self::identifiant(pole) == identifiant.to_lowercase())
+}
+
+fn rfc2822(date: i64) -> String {
+    DateTime::<Utc>::from_timestamp(date, 0).unwrap_or_default().to_rfc2822()
+}
+
+/// Nom du fil au moment du changement, ou son nom actuel s’il n’a pas été retenu.
+fn nom<'a>(fil: &'a Fil, transition: &'a Transition) -> &'a String {
+    transition.nom.as_ref().unwrap_or(fil.get_name())
+}
+
+/// Pôle du fil au moment du changement, ou son pôle actuel s’il n’a pas été retenu.
+fn pole<'a>(fil: &'a Fil, transition: &'a Transition) -> &'a Pole {
+    transition.pole.as_ref().unwrap_or(&fil.pole)
+}
+
+fn titre(fil: &Fil, transition: &Transition) -> String {
+    let nom = nom(fil, transition);
+    match transition.vers {
+        Status::Vote => format!("« {nom} » passe au vote"),
+        Status::Termine => format!("« {nom} » terminé"),
+        Status::EnDev => format!("« {nom} » passe en développement"),
+        Status::Discussion => format!("« {nom} » repasse en discussion"),
+        Status::Inconnu => format!("« {nom} » : statut inconnu")
+    }
+}
+
+fn item(fil: &Fil, transition: &Transition) -> Item {
+    let mut description = format!("Pôle {} : {} → {}.", pole(fil, transition), transition.de, transition.vers);
+    if let Some(auteur) = &transition.auteur {
+        description += format!(" Par {auteur}.").as_str();
+    }
+    let date = transition.date.unix_timestamp();
+    ItemBuilder::default()
+        .title(Some(titre(fil, transition)))
+        .link(Some(fil.get_lien().clone()))
+        .description(Some(description))
+        .pub_date(Some(rfc2822(date)))
+        .category(CategoryBuilder::default().name(pole(fil, transition).to_string()).build())
+        .guid(Some(GuidBuilder::default().value(format!("{}-{date}-{}", fil.get_id(), identifiant_statut(&transition.vers)))
+            .permalink(false).build()))
+        .build()
+}
+
+fn identifiant_statut(statut: &Status) -> String {
+    format!("{statut:?}").to_lowercase()
+}
+
+/// Génère le flux RSS des derniers changements de statut, de tous les pôles ou d’un seul. Chaque changement
+/// est présenté avec le nom et le pôle qu’avait alors le fil.
+pub fn generer<'a>(fils: impl Iterator<Item = &'a Fil>, pole: Option<&Pole>) -> String {
+    let config = &config::get().flux;
+    let mut transitions: Vec<(&Fil, &Transition)> = fils
+        .flat_map(|fil| fil.get_historique().iter().map(move |transition| (fil, transition)))
+        .filter(|(fil, transition)| pole.is_none_or(|pole| self::pole(fil, transition) == pole))
+        .collect();
+    transitions.sort_by_key(|(_, transition)| Reverse(transition.date.unix_timestamp()));
+    transitions.truncate(config.taille);
+
+    let (titre, description) = match pole {
+        Some(pole) => (format!("Staff — {pole}"), format!("Changements de statut des fils du pôle {pole}.")),
+        None => ("Staff".to_string(), "Changements de statut des fils du staff.".to_string())
+    };
+    ChannelBuilder::default()
+        .title(titre)
+        .link(config.lien.clone())
+        .description(description)
+        .language(Some("fr".to_string()))
+        .last_build_date(transitions.first().map(|(_, transition)| rfc2822(transition.date.unix_timestamp())))
+        .items(transitions.into_iter().map(|(fil, transition)| item(fil, transition)).collect::<Vec<Item>>())
+        .build()
+        .to_string()
+}
+
+/// Génère le flux global (`tous.xml`) et celui de chaque pôle, avec leur nom de fichier.
+fn fichiers<'a>(fils: impl Iterator<Item = &'a Fil> + Clone) -> Vec<(String, String)> {
+    let mut fichiers = vec![("tous.xml".to_string(), generer(fils.clone(), None))];
+    for pole in Pole::iter() {
+        fichiers.push((format!("{}.xml", identifiant(&pole)), generer(fils.clone(), Some(&pole))));
+    }
+    fichiers
+}
+
+/// Écrit les flux générés dans le dossier configuré, s’il y en a un.
+fn ecrire(fichiers: Vec<(String, String)>) {
+    let Some(dossier) = &config::get().flux.dossier else {
+        return;
+    };
+    let resultat = fs::create_dir_all(dossier).map_err(ErrType::from).and_then(|_| fichiers.into_iter()
+        .try_for_each(|(nom, contenu)| stockage::ecrire_atomique(&Path::new(dossier).join(nom), contenu.as_bytes())));
+    if let Err(e) = resultat {
+        tracing::error!(erreur = %e, dossier, "Erreur lors de l’écriture des flux RSS");
+    }
+}
+
+/// Écrit les flux dans le dossier configuré, s’il y en a un.
+pub fn ecrire_fichiers<'a>(fils: impl Iterator<Item = &'a Fil> + Clone) {
+    if config::get().flux.dossier.is_some() {
+        ecrire(fichiers(fils));
+    }
+}
+
+/// Réécrit les flux en tâche de fond si des changements en modifient les entrées : changement de statut,
+/// ajout ou suppression d’un fil.
+pub fn repercuter(evenements: &[Evenement]) {
+    if config::get().flux.dossier.is_none() || !evenements.iter()
+        .any(|evenement| matches!(evenement.action, Action::Statut(_, _) | Action::Ajout | Action::Suppression)) {
+        return;
+    }
+    tokio::spawn(async {
+        if let Some(bot) = stockage::bot() {
+            let fichiers = fichiers(bot.lock().await.database.values());
+            ecrire(fichiers);
+        }
+    });
+}
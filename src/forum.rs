@@ -0,0 +1,142 @@
+use serenity::all::{ChannelId, CreateForumPost, CreateMessage, EditMessage, EditThread, ForumTag, ForumTagId, Http, MessageId};
+use tokio::sync::Mutex;
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::audit::{Action, Evenement};
+use crate::config;
+use crate::discord;
+use crate::entete;
+use crate::fil::fields::Status;
+use crate::fil::Fil;
+use crate::stockage;
+
+/// Longueur maximale du titre d’un post Discord.
+const TITRE_MAX: usize = 100;
+
+/// Les synchronisations passent l’une après l’autre, pour qu’un post soit créé avant d’être mis à jour.
+static SYNCHRONISATION: Mutex<()> = Mutex::const_new(());
+
+fn titre(fil: &Fil) -> String {
+    fil.get_name().chars().take(TITRE_MAX).collect()
+}
+
+/// Parmi les étiquettes disponibles, celles portant le nom du statut ou du pôle du fil, sans tenir compte de la casse.
+fn choisir(disponibles: &[ForumTag], fil: &Fil) -> Vec<ForumTagId> {
+    let noms = [fil.status.to_string().to_lowercase(), fil.pole.to_string().to_lowercase()];
+    disponibles.iter()
+        .filter(|etiquette| noms.contains(&etiquette.name.to_lowercase()))
+        .map(|etiquette| etiquette.id)
+        .collect()
+}
+
+/// Étiquettes du salon forum portant le nom du statut ou du pôle du fil.
+async fn etiquettes(http: &Http, salon: ChannelId, fil: &Fil) -> Result<Vec<ForumTagId>, ErrType> {
+    let forum = salon.to_channel(http).await?.guild().ok_or(ErrType::NoneError)?;
+    Ok(choisir(&forum.available_tags, fil))
+}
+
+async fn creer(http: &Http, salon: ChannelId, fil: &Fil) -> Result<u64, ErrType> {
+    let post = salon.create_forum_post(http, CreateForumPost::new(titre(fil), CreateMessage::new().embed(fil.get_embed()))
+        .set_applied_tags(etiquettes(http, salon, fil).await?)).await?;
+    if fil.status == Status::Termine {
+        post.id.edit_thread(http, EditThread::new().archived(true).locked(true)).await?;
+    }
+    Ok(post.id.get())
+}
+
+/// Met à jour le titre, les étiquettes et le premier message du post, puis l’archive et le verrouille
+/// si le fil est terminé.
+async fn mettre_a_jour(http: &Http, salon: ChannelId, post: u64, fil: &Fil) -> Result<(), ErrType> {
+    let post = ChannelId::new(post);
+    /* Un post archivé n’est modifiable qu’en le désarchivant dans la même requête. */
+    post.edit_thread(http, EditThread::new().name(titre(fil)).applied_tags(etiquettes(http, salon, fil).await?)
+        .archived(false).locked(false)).await?;
+    post.edit_message(http, MessageId::new(post.get()), EditMessage::new().embed(fil.get_embed())).await?;
+    if fil.status == Status::Termine {
+        post.edit_thread(http, EditThread::new().archived(true).locked(true)).await?;
+    }
+    Ok(())
+}
+
+async fn synchroniser(fils: Vec<(u64, bool)>) {
+    let (Some(forum), Some(http), Some(bot)) = (&config::get().forum, discord::http(), stockage::bot()) else {
+        return;
+    };
+    let salon = ChannelId::new(forum.salon);
+    let _verrou = SYNCHRONISATION.lock().await;
+    for (id, ajout) in fils {
+        /* État courant du fil, qui a pu changer depuis le constat (post créé entre-temps…). */
+        let Some(fil) = bot.lock().await.database.get(&id).cloned() else {
+            continue;
+        };
+        match fil.post {
+            Some(post) => if let Err(e) = mettre_a_jour(&http, salon, post, &fil).await {
+                tracing::error!(erreur = %e, fil = id, post, "Erreur lors de la mise à jour du post du fil");
+            },
+            None if ajout => match creer(&http, salon, &fil).await {
+                Ok(post) => {
+                    let mut bot = bot.lock().await;
+                    if let Some(fil) = bot.database.get_mut(&id) {
+                        fil.post = Some(post);
+                        entete::signaler(&mut bot);
+                        if let Err(e) = stockage::enregistrer(&mut bot) {
+                            tracing::error!(erreur = %e, fil = id, post, "Erreur lors de l’enregistrement du post du fil");
+                        }
+                    }
+                }
+                Err(e) => tracing::error!(erreur = %e, fil = id, salon = forum.salon, "Erreur lors de la création du post du fil")
+            },
+            None => {}
+        }
+    }
+}
+
+/// Répercute les changements constatés sur les posts du salon forum, s’il est configuré : création
+/// du post des nouveaux fils, mise à jour de ceux dont le nom, le pôle, le statut, l’échéance ou
+/// les responsables ont changé.
+pub fn repercuter(evenements: &[Evenement]) {
+    if config::get().forum.is_none() {
+        return;
+    }
+    let mut fils: Vec<(u64, bool)> = Vec::new();
+    for evenement in evenements {
+        let ajout = match evenement.action {
+            Action::Ajout => true,
+            Action::Suppression | Action::Note(_) => continue,
+            _ => false
+        };
+        let id = evenement.fil.get_id();
+        match fils.iter_mut().find(|(autre, _)| *autre == id) {
+            Some((_, deja)) => *deja |= ajout,
+            None => fils.push((id, ajout))
+        }
+    }
+    if !fils.is_empty() {
+        tokio::spawn(synchroniser(fils));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stockage;
+
+    use super::*;
+
+    fn etiquette(id: u64, nom: &str) -> ForumTag {
+        serde_json::from_value(serde_json::json!({ "id": id.to_string(), "name": nom, "moderated": false })).unwrap()
+    }
+
+    #[test]
+    fn etiquettes_du_statut_et_du_pole() {
+        let disponibles = [etiquette(1, "Discussion"), etiquette(2, "VOTE"), etiquette(3, "technique"),
+            etiquette(4, "Légal"), etiquette(5, "Terminé")];
+        let fils = stockage::tests::fixture("v2.yml");
+        /* Guide : Technique, au vote. */
+        assert_eq!(choisir(&disponibles, &fils[0]), [ForumTagId::new(2), ForumTagId::new(3)]);
+        /* Charte : Légal, terminée. */
+        assert_eq!(choisir(&disponibles, &fils[1]), [ForumTagId::new(4), ForumTagId::new(5)]);
+        /* Sans étiquette correspondante, le post n’en reçoit aucune. */
+        assert!(choisir(&disponibles[..1], &fils[0]).is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+use yaml_rust2::{Yaml, YamlLoader};
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+
+/// Erreur de lecture du fichier d’import.
+pub fn erreur(e: impl Display) -> ErrType {
+    io::Error::other(format!("Fichier d’import illisible : {e}")).into()
+}
+
+/// Ligne d’un fichier d’import, telle que lue avant validation.
+pub struct Ligne {
+    pub numero: usize,
+    pub nom: String,
+    pub url: String,
+    pub pole: String,
+    pub status: String
+}
+
+/// Effet qu’aurait (ou qu’a eu) une ligne d’import sur la base de données.
+pub enum Resultat {
+    Ajout(Fil),
+    MiseAJour(Fil, Vec<String>),
+    Inchange(u64),
+    Erreur(String)
+}
+
+impl Display for Resultat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Resultat::Ajout(fil) => write!(f, "ajout de « {} » ({})", fil.get_name(), fil.get_id()),
+            Resultat::MiseAJour(fil, changements) => write!(f, "mise à jour de « {} » ({}) : {}", fil.get_name(), fil.get_id(), changements.join(", ")),
+            Resultat::Inchange(id) => write!(f, "fil {id} déjà à jour"),
+            Resultat::Erreur(e) => write!(f, "**erreur** : {e}")
+        }
+    }
+}
+
+fn colonne(nom: &str) -> Option<&'static str> {
+    match nom.trim().to_lowercase().as_str() {
+        "nom" | "name" | "titre" => Some("nom"),
+        "url" | "lien" | "link" => Some("url"),
+        "pole" | "pôle" => Some("pole"),
+        "status" | "statut" => Some("status"),
+        _ => None
+    }
+}
+
+/// Lit un fichier d’import CSV (avec ligne d’en-tête) ou YAML (liste de fils), selon son extension.
+pub fn lire(nom_fichier: &str, contenu: &str) -> Result<Vec<Ligne>, ErrType> {
+    let nom_fichier = nom_fichier.to_lowercase();
+    if nom_fichier.ends_with(".csv") {
+        lire_csv(contenu)
+    } else if nom_fichier.ends_with(".yml") || nom_fichier.ends_with(".yaml") {
+        lire_yaml(contenu)
+    } else {
+        Err(ErrType::ObjectNotFound(format!("Format de fichier non reconnu pour {nom_fichier} (csv ou yml attendu).")))
+    }
+}
+
+fn lire_csv(contenu: &str) -> Result<Vec<Ligne>, ErrType> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(contenu.as_bytes());
+    let en_tetes: Vec<Option<&str>> = reader.headers()
+        .map_err(erreur)?
+        .iter().map(colonne).collect();
+    let mut lignes = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(erreur)?;
+        let mut valeurs = HashMap::new();
+        for (en_tete, valeur) in en_tetes.iter().zip(record.iter()) {
+            if let Some(en_tete) = en_tete {
+                valeurs.insert(*en_tete, valeur.trim().to_string());
+            }
+        }
+        lignes.push(Ligne {
+            numero: i + 2,
+            nom: valeurs.remove("nom").unwrap_or_default(),
+            url: valeurs.remove("url").unwrap_or_default(),
+            pole: valeurs.remove("pole").unwrap_or_default(),
+            status: valeurs.remove("status").unwrap_or_default()
+        });
+    }
+    Ok(lignes)
+}
+
+fn lire_yaml(contenu: &str) -> Result<Vec<Ligne>, ErrType> {
+    let documents = YamlLoader::load_from_str(contenu).map_err(|e| ErrType::YamlParseError(e.to_string()))?;
+    let entrees = documents.first().and_then(Yaml::as_vec)
+        .ok_or(ErrType::YamlParseError("Le fichier YAML doit contenir une liste de fils.".to_string()))?;
+    Ok(entrees.iter().enumerate().map(|(i, entree)| {
+        let mut valeurs = HashMap::new();
+        if let Some(hash) = entree.as_hash() {
+            for (cle, valeur) in hash {
+                if let (Some(cle), Some(valeur)) = (cle.as_str().and_then(colonne), valeur.as_str()) {
+                    valeurs.insert(cle, valeur.trim().to_string());
+                }
+            }
+        }
+        Ligne {
+            numero: i + 1,
+            nom: valeurs.remove("nom").unwrap_or_default(),
+            url: valeurs.remove("url").unwrap_or_default(),
+            pole: valeurs.remove("pole").unwrap_or_default(),
+            status: valeurs.remove("status").unwrap_or_default()
+        }
+    }).collect())
+}
+
+/// Calcule sans rien modifier l’effet de chaque ligne sur la base de données.
+pub fn analyser(database: &HashMap<u64, Fil>, lignes: &[Ligne]) -> Vec<(usize, Resultat)> {
+    let mut vus: Vec<u64> = Vec::new();
+    lignes.iter().map(|ligne| (ligne.numero, analyser_ligne(database, ligne, &mut vus))).collect()
+}
+
+fn analyser_ligne(database: &HashMap<u64, Fil>, ligne: &Ligne, vus: &mut Vec<u64>) -> Resultat {
+    let Some(id) = Fil::find_id(&ligne.url) else {
+        return Resultat::Erreur(format!("URL « {} » malformée, impossible de déterminer l’identifiant du fil.", ligne.url));
+    };
+    if vus.contains(&id) {
+        return Resultat::Erreur(format!("fil {id} présent plusieurs fois dans le fichier."));
+    }
+    vus.push(id);
+    let pole = match Pole::from_str(ligne.pole.as_str()) {
+        Ok(pole) => pole,
+        Err(_) => return Resultat::Erreur(format!("pôle « {} » inconnu.", ligne.pole))
+    };
+    let status = match Status::from_str(ligne.status.as_str()) {
+        Ok(status) => status,
+        Err(_) => return Resultat::Erreur(format!("statut « {} » inconnu.", ligne.status))
+    };
+
+    match database.get(&id) {
+        None => {
+            if ligne.nom.is_empty() {
+                return Resultat::Erreur("nom manquant.".to_string());
+            }
+            Resultat::Ajout(Fil::new(ligne.nom.clone(), ligne.url.clone(), pole, status))
+        }
+        Some(existant) => {
+            let mut fil = existant.clone();
+            let mut changements = Vec::new();
+            if !ligne.nom.is_empty() && ligne.nom != *fil.get_name() {
+                changements.push(format!("nom « {} » → « {} »", fil.get_name(), ligne.nom));
+                fil.set_name(ligne.nom.clone());
+            }
+            if pole != fil.pole {
+                changements.push(format!("pôle {} → {pole}", fil.pole));
+                fil.pole = pole;
+            }
+            if status != fil.status {
+                changements.push(format!("statut {} → {status}", fil.status));
+                fil.set_status(status, None);
+            }
+            if changements.is_empty() {
+                Resultat::Inchange(id)
+            } else {
+                fil.set_modified(true);
+                Resultat::MiseAJour(fil, changements)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stockage;
+
+    use super::*;
+
+    #[test]
+    fn lit_les_fichiers() {
+        let lignes = lire("fils.CSV", "Titre,Lien,Pôle,Statut,Commentaire\n\
+            Refonte du guide, http://commandemento5.wikidot.com/forum/t-1234567 ,Technique,Vote,ignoré\n\
+            Incomplet\n").unwrap();
+        assert_eq!(lignes.len(), 2);
+        assert_eq!((lignes[0].numero, lignes[0].url.as_str(), lignes[0].status.as_str()),
+                   (2, "http://commandemento5.wikidot.com/forum/t-1234567", "Vote"));
+        assert!(lignes[1].url.is_empty());
+
+        let lignes = lire("fils.yml", "- nom: Charte\n  url: http://commandemento5.wikidot.com/forum/t-7654321\n  pole: Légal\n  status: Vote\n").unwrap();
+        assert_eq!((lignes[0].numero, lignes[0].pole.as_str()), (1, "Légal"));
+        assert!(lire("fils.yml", "nom: pas une liste").is_err());
+        assert!(lire("fils.txt", "").is_err());
+    }
+
+    #[test]
+    fn valide_chaque_ligne() {
+        let database: HashMap<u64, Fil> = stockage::tests::fixture("v2.yml").into_iter().map(|fil| (fil.get_id(), fil)).collect();
+        let ligne = |nom: &str, url: &str, pole: &str, status: &str| Ligne {
+            numero: 0, nom: nom.to_string(), url: format!("http://commandemento5.wikidot.com/forum/{url}"),
+            pole: pole.to_string(), status: status.to_string()
+        };
+        let resultats = analyser(&database, &[
+            ligne("Nouveau fil", "t-42", "Technique", "Discussion"),
+            ligne("", "t-1234567", "Technique", "Terminé"),
+            ligne("", "t-1111111", "Technique", "Discussion"),
+            ligne("Doublon", "t-42", "Technique", "Discussion"),
+            ligne("", "t-43", "Technique", "Discussion"),
+            ligne("Pôle inconnu", "t-44", "Cuisine", "Discussion"),
+            ligne("Lien invalide", "c-6827478", "Technique", "Discussion")
+        ]);
+        let resultats: Vec<&Resultat> = resultats.iter().map(|(_, resultat)| resultat).collect();
+        assert!(matches!(resultats[0], Resultat::Ajout(fil) if fil.get_id() == 42));
+        match resultats[1] {
+            /* Un nom vide ne renomme pas un fil existant. */
+            Resultat::MiseAJour(fil, changements) => {
+                assert_eq!(fil.get_name(), "Refonte du guide");
+                assert_eq!(changements, &vec!["statut Vote → Terminé".to_string()]);
+                assert_eq!(fil.get_historique().last().map(|transition| &transition.vers), Some(&Status::Termine));
+            }
+            _ => panic!("mise à jour attendue")
+        }
+        assert!(matches!(resultats[2], Resultat::Inchange(1111111)));
+        assert!(matches!(resultats[3], Resultat::Erreur(e) if e.contains("plusieurs fois")));
+        assert!(matches!(resultats[4], Resultat::Erreur(e) if e == "nom manquant."));
+        assert!(matches!(resultats[5], Resultat::Erreur(e) if e.contains("Cuisine")));
+        assert!(matches!(resultats[6], Resultat::Erreur(e) if e.contains("malformée")));
+    }
+}
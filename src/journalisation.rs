@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::io;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use serenity::all::Timestamp;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as format, Layer};
+
+use crate::config::{FormatJournal, Journalisation};
+
+/// Derniers avertissements et erreurs, consultables avec `/journal`.
+static MEMOIRE: Mutex<VecDeque<Entree>> = Mutex::new(VecDeque::new());
+
+/// Avertissement ou erreur gardé en mémoire.
+#[derive(Clone, Debug)]
+pub struct Entree {
+    pub date: Timestamp,
+    pub niveau: Level,
+    pub message: String,
+    /// Champs structurés de l’événement (flux, fil, action, utilisateur…), dans l’ordre.
+    pub champs: Vec<(String, String)>
+}
+
+impl fmt::Display for Entree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.date.format("%d/%m %H:%M:%S"), self.niveau, self.message)?;
+        for (nom, valeur) in &self.champs {
+            write!(f, " {nom}={valeur}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Visiteur {
+    message: String,
+    champs: Vec<(String, String)>
+}
+
+impl Visit for Visiteur {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.champs.push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.champs.push((field.name().to_string(), format!("{value:?}")));
+        }
+    }
+}
+
+/// Couche gardant en mémoire les `taille` derniers avertissements et erreurs.
+struct Memoire {
+    taille: usize
+}
+
+impl<S: Subscriber> Layer<S> for Memoire {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let niveau = *event.metadata().level();
+        if niveau > Level::WARN || self.taille == 0 {
+            return;
+        }
+        let mut visiteur = Visiteur::default();
+        event.record(&mut visiteur);
+        let mut memoire = MEMOIRE.lock().unwrap();
+        if memoire.len() >= self.taille {
+            memoire.pop_front();
+        }
+        memoire.push_back(Entree { date: Timestamp::now(), niveau, message: visiteur.message, champs: visiteur.champs });
+    }
+}
+
+/// Installe la journalisation selon la configuration. Les messages sont écrits sur la sortie d’erreur.
+///
+/// Le niveau configuré s’applique aux messages du bot ; seuls les avertissements et erreurs
+/// des bibliothèques (serenity, poise…) sont affichés.
+pub fn init(config: &Journalisation) {
+    let niveau = LevelFilter::from_str(config.niveau.as_str()).unwrap_or_else(|_| {
+        eprintln!("Niveau de journalisation inconnu : {}. Utilisation de « info ».", config.niveau);
+        LevelFilter::INFO
+    });
+    let filtre = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), niveau)
+        .with_default(LevelFilter::WARN);
+    let sortie = match config.format {
+        FormatJournal::Humain => format::layer().with_writer(io::stderr).boxed(),
+        FormatJournal::Json => format::layer().json().with_writer(io::stderr).boxed()
+    };
+    if let Err(e) = tracing_subscriber::registry()
+        .with(sortie.with_filter(filtre))
+        .with(Memoire { taille: config.memoire })
+        .try_init() {
+        eprintln!("La journalisation a déjà été initialisée : {e}");
+    }
+}
+
+/// Renvoie les `nombre` derniers avertissements et erreurs, du plus ancien au plus récent.
+pub fn dernieres(nombre: usize) -> Vec<Entree> {
+    let memoire = MEMOIRE.lock().unwrap();
+    memoire.iter().skip(memoire.len().saturating_sub(nombre)).cloned().collect()
+}
+
+/// Comme `try_loop!` de fondabots-lib, mais journalise l’erreur comme avertissement avec des champs structurés.
+///
+/// `essayer!(expression, champ = valeur, …, "message")` passe à l’itération suivante en cas d’erreur.
+#[macro_export]
+macro_rules! essayer {
+    ($expression:expr, $($message:tt)+) => {
+        match $expression {
+            Ok(valeur) => valeur,
+            Err(erreur) => {
+                tracing::warn!(erreur = %erreur, $($message)+);
+                continue;
+            }
+        }
+    };
+}
@@ -0,0 +1,116 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use regex::Regex;
+use rss::Channel;
+
+use fondabots_lib::ErrType;
+
+use crate::fil::fields::{Pole, Status};
+use crate::fil::{Fil, CATEGORIES};
+
+/// Délai maximal de chaque requête au forum pour deviner les informations d’un fil.
+const DELAI: Duration = Duration::from_secs(5);
+
+/// Liens de fils du forum wikidot présents dans un texte, avec leur identifiant, sans doublons.
+pub fn extraire(texte: &str) -> Vec<(u64, String)> {
+    static REGEX_LIENS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"https?://[\w.-]+\.wikidot\.com/forum/t-\d+[^\s<>|)]*").unwrap());
+    let mut liens: Vec<(u64, String)> = Vec::new();
+    for lien in REGEX_LIENS.find_iter(texte) {
+        /* La ponctuation qui suit un lien dans une phrase n’en fait pas partie. */
+        let lien = lien.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']).to_string();
+        if let Some(id) = Fil::find_id(&lien) {
+            if !liens.iter().any(|(autre, _)| *autre == id) {
+                liens.push((id, lien));
+            }
+        }
+    }
+    liens
+}
+
+/// Informations d’un fil devinées depuis le forum, à faire confirmer avant de l’enregistrer.
+#[derive(Clone, Debug)]
+pub struct Proposition {
+    pub nom: String,
+    pub pole: Pole,
+    pub statut: Status,
+    pub reponses: Option<u32>
+}
+
+/// Devine le nom, le statut et le nombre de réponses d’un fil depuis son flux RSS, et son pôle depuis la catégorie
+/// du forum à laquelle renvoie sa page. Ce qui n’a pu être deviné prend une valeur par défaut.
+pub async fn deviner(lien: &str) -> Proposition {
+    let mut proposition = Proposition { nom: String::new(), pole: Pole::Autre, statut: Status::Discussion, reponses: None };
+    let (Some(id), Some(site)) = (Fil::find_id(&lien.to_string()), lien.find("/forum/").map(|fin| &lien[..fin])) else {
+        return proposition;
+    };
+    let client = match reqwest::Client::builder().timeout(DELAI).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!(erreur = %e, "Client HTTP indisponible pour deviner un fil");
+            return proposition;
+        }
+    };
+
+    let flux = format!("{site}/feed/forum/t-{id}.xml");
+    match async { Ok::<_, ErrType>(Channel::read_from(&client.get(&flux).send().await?.bytes().await?[..])?) }.await {
+        Ok(rss) => {
+            proposition.nom = Fil::nom_depuis_titre(rss.title()).unwrap_or(rss.title().to_string());
+            proposition.statut = Fil::statut_depuis_titre(rss.title());
+            proposition.reponses = Some(reponses(&rss));
+        }
+        Err(e) => tracing::debug!(erreur = %e, flux, "Flux RSS du fil inaccessible")
+    }
+
+    match async { Ok::<_, ErrType>(client.get(lien).send().await?.text().await?) }.await {
+        Ok(page) => if let Some(pole) = pole_depuis_page(page.as_str()) {
+            proposition.pole = pole;
+        },
+        Err(e) => tracing::debug!(erreur = %e, lien, "Page du fil inaccessible")
+    }
+    proposition
+}
+
+/// Fil d’Ariane de la page d’un fil (« Forum » » catégorie » fil), seul à renvoyer à coup sûr à sa catégorie.
+static REGEX_ARIANE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?s)class="forum-breadcrumbs".*?</div>"#).unwrap());
+static REGEX_CATEGORIE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/forum/c-(\d+)").unwrap());
+
+/// Pôle de la catégorie du forum à laquelle renvoie le fil d’Ariane de la page d’un fil, si elle est suivie.
+/// Les autres liens de la page (menu du site…) peuvent mener à d’autres catégories.
+pub fn pole_depuis_page(page: &str) -> Option<Pole> {
+    let ariane = REGEX_ARIANE.find(page)?.as_str();
+    let categorie = REGEX_CATEGORIE.captures(ariane).and_then(|captures| captures[1].parse::<u64>().ok())?;
+    CATEGORIES.iter().find(|(_, autre)| *autre == categorie).map(|(pole, _)| pole.clone())
+}
+
+/// Nombre de réponses d’un fil d’après son flux, dont la première entrée est le message d’ouverture.
+/// Le flux ne liste que les derniers messages d’un long fil : c’est alors un minimum.
+pub fn reponses(rss: &Channel) -> u32 {
+    rss.items.len().saturating_sub(1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extrait_les_liens() {
+        let liens = extraire("Voir http://commandemento5.wikidot.com/forum/t-1234567/refonte-du-guide et \
+            <https://commandemento5.wikidot.com/forum/t-1234567> (doublon), [ici](http://commandemento5.wikidot.com/forum/t-42). \
+            Enfin, http://commandemento5.wikidot.com/forum/t-7/charte, puis http://commandemento5.wikidot.com/forum/t-8/guide.");
+        assert_eq!(liens, vec![
+            (1234567, "http://commandemento5.wikidot.com/forum/t-1234567/refonte-du-guide".to_string()),
+            (42, "http://commandemento5.wikidot.com/forum/t-42".to_string()),
+            (7, "http://commandemento5.wikidot.com/forum/t-7/charte".to_string()),
+            (8, "http://commandemento5.wikidot.com/forum/t-8/guide".to_string())
+        ]);
+        assert!(extraire("http://commandemento5.wikidot.com/forum/c-6827478").is_empty());
+    }
+
+    #[test]
+    fn pole_du_fil_d_ariane() {
+        let page = std::fs::read_to_string(format!("{}/tests/fixtures/page-fil.html", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        assert_eq!(pole_depuis_page(page.as_str()), Some(Pole::Technique));
+        assert_eq!(pole_depuis_page("<a href=\"/forum/c-1905799/legal\">Légal</a>"), None);
+    }
+}
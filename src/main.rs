@@ -1,40 +1,110 @@
 use std::env;
+use std::process;
 
 use fondabots_lib::affichan::Affichan;
 use fondabots_lib::Bot;
 use maplit::hashmap;
 use serenity::all::{ChannelId, GatewayIntents};
 
-use crate::fil::fields::Status;
 use crate::fil::Fil;
 
 mod fil;
+mod api;
 mod commands;
+mod export;
+mod stockage;
+mod cli;
+mod import;
+mod config;
+mod discord;
+mod audit;
+mod flux;
+mod forum;
+mod calendrier;
+mod rapport;
+mod stats;
+mod wikidot;
+mod ordre_du_jour;
+mod journalisation;
+mod abonnements;
+mod annonces;
+mod liens;
+mod apercus;
+mod entete;
+mod verification;
+mod passerelle;
 
 type DataType = fondabots_lib::DataType<Fil>;
 
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = env::args().collect();
-    if let Some(token) = args.get(1) {
-        match Bot::new(
-            token.clone(),
-            GatewayIntents::GUILD_MESSAGES | GatewayIntents::GUILD_MEMBERS,
-            "./staffbot.yml",
-            commands::command_list(),
-            vec![
-                Affichan::new(ChannelId::new(1265001559373119493), Box::new(| fil | {
-                    fil.status == Status::Vote
-                }))
-            ],
-            hashmap! {
-
+    match config::Config::charger(config::CHEMIN_PAR_DEFAUT) {
+        Ok(config) => config::init(config),
+        Err(e) => panic!("Erreur lors du chargement de la configuration: {e}")
+    }
+    journalisation::init(&config::get().journalisation);
+    match args.get(1).map(String::as_str) {
+        Some("exporter") => if let Err(e) = cli::exporter(&args[2..]) {
+            tracing::error!(erreur = %e, "Erreur lors de l’export");
+            process::exit(1);
+        }
+        Some("rapport") => if let Err(e) = cli::rapport(&args[2..]) {
+            tracing::error!(erreur = %e, "Erreur lors de la génération du rapport");
+            process::exit(1);
+        }
+        Some("servir") => if let Err(e) = cli::servir(&args[2..]).await {
+            tracing::error!(erreur = %e, "Erreur lors de l’exécution de l’API HTTP");
+            process::exit(1);
+        }
+        #[cfg(feature = "sqlite")]
+        Some("migrer-sqlite") => if let Err(e) = cli::migrer_sqlite(&args[2..]) {
+            tracing::error!(erreur = %e, "Erreur lors de la migration");
+            process::exit(1);
+        }
+        /* Tout autre argument doit avoir la forme d’un jeton, pour qu’une commande mal tapée ne lance pas le bot. */
+        Some(token) if serenity::utils::validate_token(token).is_ok() => {
+            if let Err(e) = stockage::sauvegardes::recuperer(stockage::BASE) {
+                panic!("Erreur lors de la vérification de la base de données: {e}");
             }
-        ).await {
-            Ok(mut bot) => if let Err(e) = bot.start().await {
-                panic!("Erreur lors de l’exécution du bot: {e}");
+            discord::init(token);
+            match Bot::new(
+                token.to_string(),
+                GatewayIntents::GUILD_MESSAGES | GatewayIntents::GUILD_MEMBERS | GatewayIntents::MESSAGE_CONTENT,
+                stockage::BASE,
+                commands::command_list(),
+                entete::AFFICHANS.iter().map(|(salon, statut)| {
+                    let statut = statut.clone();
+                    Affichan::new(ChannelId::new(*salon), Box::new(move | fil: &Fil | {
+                        fil.status == statut
+                    }))
+                }).collect(),
+                hashmap! {
+
+                }
+            ).await {
+                Ok(mut bot) => {
+                    if let Err(e) = stockage::demarrer(&mut bot) {
+                        panic!("Erreur lors de l’ouverture de la base de données: {e}");
+                    }
+                    if let Some(api) = &config::get().api {
+                        tokio::spawn(async {
+                            if let Err(e) = api::servir(api::Source::Bot, api.adresse.as_str(), api.jeton.clone()).await {
+                                tracing::error!(erreur = %e, "Erreur lors de l’exécution de l’API HTTP");
+                            }
+                        });
+                    }
+                    tokio::spawn(ordre_du_jour::planifier());
+                    tokio::spawn(abonnements::surveiller());
+                    tokio::spawn(fil::releve::planifier());
+                    tokio::spawn(verification::planifier());
+                    if let Err(e) = bot.start().await {
+                        panic!("Erreur lors de l’exécution du bot: {e}");
+                    }
+                }
+                Err(e) => panic!("Erreur lors du chargement du bot: {e}")
             }
-            Err(e) => panic!("Erreur lors du chargement du bot: {e}")
         }
+        _ => cli::usage()
     }
 }
\ No newline at end of file
@@ -0,0 +1,243 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Europe::Paris;
+use poise::ChoiceParameter;
+use serenity::all::{CreateAttachment, CreateMessage, Timestamp};
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::config;
+use crate::config::Planification;
+use crate::discord;
+use crate::export;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+use crate::flux;
+use crate::stockage;
+use crate::wikidot;
+
+/// Au-delà, l’ordre du jour est envoyé en pièce jointe plutôt que dans le message.
+pub const LONGUEUR_MAX: usize = 1900;
+
+#[derive(Clone, Copy, PartialEq, Eq, ChoiceParameter, Debug)]
+pub enum Presentation {
+    Discord,
+    Wikidot
+}
+
+/// Points à aborder en réunion du staff.
+pub struct OrdreDuJour<'a> {
+    date: DateTime<Utc>,
+    /// Votes en cours, par échéance.
+    votes: Vec<&'a Fil>,
+    /// Fils passés récemment en développement.
+    en_dev: Vec<&'a Fil>,
+    /// Discussions sans mise à jour depuis longtemps.
+    discussions: Vec<&'a Fil>
+}
+
+fn date(timestamp: &Timestamp) -> DateTime<Utc> {
+    DateTime::from_timestamp(timestamp.unix_timestamp(), 0).unwrap_or_default()
+}
+
+/// Lit une liste de pôles séparés par des virgules, désignés par leur nom ou leur identifiant.
+pub fn lire_poles(texte: &str) -> Result<Vec<Pole>, String> {
+    texte.split(',').map(str::trim).filter(|nom| !nom.is_empty())
+        .map(|nom| Pole::from_str(nom).ok().or(flux::pole_depuis_identifiant(nom))
+            .ok_or(format!("Pôle « {nom} » inexistant.")))
+        .collect()
+}
+
+impl<'a> OrdreDuJour<'a> {
+    /// Prépare l’ordre du jour à partir des fils des pôles donnés (tous si la liste est vide).
+    pub fn preparer(fils: impl Iterator<Item = &'a Fil>, poles: &[Pole], maintenant: DateTime<Utc>) -> Self {
+        let config = &config::get().ordre_du_jour;
+        let fils = export::trier(fils.filter(|fil| poles.is_empty() || poles.contains(&fil.pole)));
+        let mut votes: Vec<&Fil> = fils.iter().copied().filter(|fil| fil.status == Status::Vote).collect();
+        votes.sort_by_key(|fil| fil.echeance.as_ref().map(Timestamp::unix_timestamp).unwrap_or(i64::MAX));
+        let en_dev = fils.iter().copied().filter(|fil| fil.status == Status::EnDev && {
+            /* Date du passage en développement, ou à défaut de la dernière mise à jour. */
+            let passage = fil.get_historique().iter().rev()
+                .find(|transition| transition.vers == Status::EnDev)
+                .map(|transition| date(&transition.date))
+                .unwrap_or(date(&fil.last_update));
+            maintenant - passage <= Duration::days(config.recents)
+        }).collect();
+        let discussions = fils.iter().copied().filter(|fil| fil.status == Status::Discussion
+            && maintenant - date(&fil.last_update) > Duration::days(config.anciennes)).collect();
+        Self { date: maintenant, votes, en_dev, discussions }
+    }
+
+    pub fn est_vide(&self) -> bool {
+        self.votes.is_empty() && self.en_dev.is_empty() && self.discussions.is_empty()
+    }
+
+    fn jour(&self) -> String {
+        self.date.with_timezone(&Paris).format("%d/%m/%Y").to_string()
+    }
+
+    fn sections(&self) -> [(String, &Vec<&'a Fil>); 3] {
+        let config = &config::get().ordre_du_jour;
+        [
+            ("Votes en cours".to_string(), &self.votes),
+            (format!("Passés en développement ces {} derniers jours", config.recents), &self.en_dev),
+            (format!("Discussions sans nouvelles depuis plus de {} jours", config.anciennes), &self.discussions)
+        ]
+    }
+
+    /// Ordre du jour en Markdown Discord.
+    pub fn to_discord(&self) -> String {
+        let mut texte = format!("## Ordre du jour du {}\n", self.jour());
+        for (titre, fils) in self.sections() {
+            if fils.is_empty() {
+                continue;
+            }
+            texte += format!("### {titre}\n").as_str();
+            for fil in fils {
+                texte += format!("- [{}]({}) ({})", fil.get_name(), fil.get_lien(), fil.pole).as_str();
+                match (&fil.status, &fil.echeance) {
+                    (Status::Vote, Some(echeance)) => texte += format!(" : fin <t:{}:R>\n", echeance.unix_timestamp()).as_str(),
+                    (Status::Discussion, _) => texte += format!(" : dernière mise à jour <t:{}:R>\n", fil.last_update.unix_timestamp()).as_str(),
+                    _ => texte += "\n"
+                }
+            }
+        }
+        if self.est_vide() {
+            texte += "Rien à signaler.\n";
+        }
+        texte
+    }
+
+    /// Ordre du jour en syntaxe wikidot, à coller dans un message du forum.
+    pub fn to_wikidot(&self) -> String {
+        let mut texte = format!("+ Ordre du jour du {}\n", self.jour());
+        for (titre, fils) in self.sections() {
+            if fils.is_empty() {
+                continue;
+            }
+            texte += format!("\n++ {titre}\n\n").as_str();
+            for fil in fils {
+                texte += format!("* {} ({})", wikidot::lien(fil.get_lien(), fil.get_name()), wikidot::echapper(fil.pole.to_string().as_str())).as_str();
+                match (&fil.status, &fil.echeance) {
+                    (Status::Vote, Some(echeance)) => texte += format!(" : fin le {}\n", wikidot::date(echeance)).as_str(),
+                    (Status::Discussion, _) => texte += format!(" : dernière mise à jour le {}\n", wikidot::date(&fil.last_update)).as_str(),
+                    _ => texte += "\n"
+                }
+            }
+        }
+        if self.est_vide() {
+            texte += "\nRien à signaler.\n";
+        }
+        texte
+    }
+
+    pub fn rendre(&self, presentation: Presentation) -> String {
+        match presentation {
+            Presentation::Discord => self.to_discord(),
+            Presentation::Wikidot => format!("```\n{}```", self.to_wikidot())
+        }
+    }
+
+    pub fn nom_fichier(&self, presentation: Presentation) -> String {
+        let extension = match presentation {
+            Presentation::Discord => "md",
+            Presentation::Wikidot => "txt"
+        };
+        format!("ordre-du-jour-{}.{extension}", self.date.with_timezone(&Paris).format("%Y-%m-%d"))
+    }
+
+    /// Texte brut de la pièce jointe, quand l’ordre du jour est trop long pour un message.
+    pub fn fichier(&self, presentation: Presentation) -> String {
+        match presentation {
+            Presentation::Discord => self.to_discord(),
+            Presentation::Wikidot => self.to_wikidot()
+        }
+    }
+}
+
+/// Prochaine publication strictement après `apres`, le jour et à l’heure de Paris donnés.
+fn prochaine(jour: Weekday, heure: NaiveTime, apres: DateTime<Utc>) -> DateTime<Utc> {
+    let mut date = apres.with_timezone(&Paris).date_naive();
+    loop {
+        if date.weekday() == jour {
+            if let Some(publication) = Paris.from_local_datetime(&date.and_time(heure)).earliest() {
+                if publication > apres {
+                    return publication.with_timezone(&Utc);
+                }
+            }
+        }
+        date += Duration::days(1);
+    }
+}
+
+async fn publier(planification: &Planification) -> Result<(), ErrType> {
+    let Some(bot) = stockage::bot() else {
+        tracing::warn!("Base de données pas encore chargée : ordre du jour non publié.");
+        return Ok(());
+    };
+    let presentation = if planification.wikidot { Presentation::Wikidot } else { Presentation::Discord };
+    let message = {
+        let bot = bot.lock().await;
+        let ordre_du_jour = OrdreDuJour::preparer(bot.database.values(), &[], Utc::now());
+        let texte = ordre_du_jour.rendre(presentation);
+        if texte.len() <= LONGUEUR_MAX {
+            CreateMessage::new().content(texte)
+        } else {
+            CreateMessage::new().content(format!("Ordre du jour du {}", ordre_du_jour.jour()))
+                .add_file(CreateAttachment::bytes(ordre_du_jour.fichier(presentation), ordre_du_jour.nom_fichier(presentation)))
+        }
+    };
+    discord::envoyer(planification.salon, message).await?;
+    Ok(())
+}
+
+/// Publie l’ordre du jour chaque semaine si la publication est configurée. Ne rend jamais la main.
+pub async fn planifier() {
+    let Some(planification) = &config::get().ordre_du_jour.planification else {
+        return;
+    };
+    tracing::info!(salon = planification.salon, jour = %planification.jour, heure = %planification.heure,
+        "Publication hebdomadaire de l’ordre du jour activée");
+    loop {
+        let maintenant = Utc::now();
+        let publication = prochaine(planification.jour, planification.heure, maintenant);
+        tokio::time::sleep((publication - maintenant).to_std().unwrap_or_default()).await;
+        if let Err(e) = publier(planification).await {
+            tracing::error!(erreur = %e, salon = planification.salon, "Erreur lors de la publication de l’ordre du jour");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stockage;
+
+    use super::*;
+
+    #[test]
+    fn prochaine_publication() {
+        let heure = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        /* Lundi 21/10/2024 à 8 h à Paris (6 h UTC) : publication le jour même. */
+        let lundi = Utc.with_ymd_and_hms(2024, 10, 21, 6, 0, 0).unwrap();
+        assert_eq!(prochaine(Weekday::Mon, heure, lundi), Utc.with_ymd_and_hms(2024, 10, 21, 7, 0, 0).unwrap());
+        /* Juste après : la semaine suivante, passée à l’heure d’hiver. */
+        let apres = Utc.with_ymd_and_hms(2024, 10, 21, 7, 0, 0).unwrap();
+        assert_eq!(prochaine(Weekday::Mon, heure, apres), Utc.with_ymd_and_hms(2024, 10, 28, 8, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn sections() {
+        let fils = stockage::tests::fixture("v2.yml");
+        /* Un mois après la dernière mise à jour de la discussion (1721500000). */
+        let date = DateTime::from_timestamp(1721500000 + 40 * 86400, 0).unwrap();
+        let ordre_du_jour = OrdreDuJour::preparer(fils.iter(), &[], date);
+        assert_eq!(ordre_du_jour.votes.len(), 1);
+        assert_eq!(ordre_du_jour.discussions.len(), 1);
+        let wikidot = ordre_du_jour.to_wikidot();
+        assert!(wikidot.contains("* [http://commandemento5.wikidot.com/forum/t-1234567/refonte-du-guide Refonte du guide] (Technique) : fin le 22/07/2024\n"));
+        assert!(OrdreDuJour::preparer(fils.iter(), &lire_poles("Légal").unwrap(), date).est_vide());
+        assert!(lire_poles("technique, Légal").unwrap() == vec![Pole::Technique, Pole::Legal]);
+        assert!(lire_poles("inconnu").is_err());
+    }
+}
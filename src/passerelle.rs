@@ -0,0 +1,31 @@
+use std::sync::OnceLock;
+
+use poise::serenity_prelude as serenity;
+use serenity::all::{Context, Event};
+use serenity::futures::StreamExt;
+
+use crate::apercus;
+
+/// Connexion du bot à la passerelle Discord, que fondabots-lib ne partage pas : elle est retenue à la
+/// première interaction reçue par le bot (commande ou bouton). fondabots-lib ne donnant accès ni à son
+/// gestionnaire d’événements ni au `pre_command` de son framework, chaque commande la transmet elle-même.
+static CONTEXTE: OnceLock<Context> = OnceLock::new();
+
+/// Retient la connexion du bot à la passerelle et commence à en écouter les événements. Seul le premier
+/// appel a un effet.
+pub fn partager(ctx: &Context) {
+    if CONTEXTE.set(ctx.clone()).is_ok() {
+        tokio::spawn(ecouter(ctx.clone()));
+    }
+}
+
+/// Répond aux messages des salons des aperçus, que fondabots-lib ne transmet pas au bot.
+async fn ecouter(ctx: Context) {
+    let mut messages = serenity::collector::collect(&ctx.shard, |evenement| match evenement {
+        Event::MessageCreate(creation) if apercus::concerne(&creation.message) => Some(creation.message.clone()),
+        _ => None
+    });
+    while let Some(message) = messages.next().await {
+        tokio::spawn(apercus::repondre(ctx.clone(), message));
+    }
+}
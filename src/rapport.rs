@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Paris;
+use strum::IntoEnumIterator;
+
+use fondabots_lib::Object;
+
+use crate::export;
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1 { margin-bottom: 0; }
+.date { color: #666; margin-top: 0.2em; }
+section { margin-top: 2em; border-left: 0.5em solid; padding-left: 1em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; }
+a { color: inherit; }
+.statut { display: inline-block; padding: 0.1em 0.6em; border-radius: 1em; font-size: 0.85em; white-space: nowrap; }
+.discussion { background: #dbeafe; color: #1e3a8a; }
+.vote { background: #fef3c7; color: #92400e; }
+.endev { background: #ede9fe; color: #5b21b6; }
+.termine { background: #dcfce7; color: #166534; }
+.inconnu { background: #e5e7eb; color: #374151; }
+@media print { section { break-inside: avoid; } }
+";
+
+fn echapper(texte: &str) -> String {
+    texte.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn date(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0).unwrap_or_default()
+        .with_timezone(&Paris).format("%d/%m/%Y %H:%M").to_string()
+}
+
+/// Couleur du pôle, assombrie si elle est trop claire pour ressortir sur une page blanche.
+fn couleur(pole: &Pole) -> String {
+    let couleur = pole.get_color();
+    let (r, g, b) = ((couleur >> 16) & 0xFF, (couleur >> 8) & 0xFF, couleur & 0xFF);
+    if r + g + b > 600 {
+        "#999999".to_string()
+    } else {
+        format!("#{couleur:06x}")
+    }
+}
+
+fn ligne(fil: &Fil) -> String {
+    let echeance = match (&fil.status, &fil.echeance) {
+        (Status::Vote, Some(echeance)) => date(echeance.unix_timestamp()),
+        _ => String::new()
+    };
+    format!("<tr><td><a href=\"{}\">{}</a></td><td><span class=\"statut {}\">{}</span></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            echapper(fil.get_lien()), echapper(fil.get_name()),
+            format!("{:?}", fil.status).to_lowercase(), echapper(fil.status.to_string().as_str()),
+            echeance, echapper(fil.get_responsables().join(", ").as_str()), date(fil.last_update.unix_timestamp()))
+}
+
+/// Génère une page HTML autonome présentant les fils, regroupés par pôle.
+pub fn generer(fils: &[&Fil]) -> String {
+    let fils = export::trier(fils.iter().copied());
+    let mut html = format!("<!DOCTYPE html>\n<html lang=\"fr\">\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>Fils du staff</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n\
+        <h1>Fils du staff</h1>\n<p class=\"date\">{} fil(s), état au {}.</p>\n", fils.len(), date(Utc::now().timestamp()));
+    for pole in Pole::iter() {
+        let fils_pole: Vec<&&Fil> = fils.iter().filter(|fil| fil.pole == pole).collect();
+        if fils_pole.is_empty() {
+            continue;
+        }
+        html += format!("<section style=\"border-color: {}\">\n<h2>{} ({})</h2>\n<table>\n\
+            <tr><th>Fil</th><th>Statut</th><th>Fin du vote</th><th>Responsables</th><th>Dernière mise à jour</th></tr>\n",
+                        couleur(&pole), echapper(pole.to_string().as_str()), fils_pole.len()).as_str();
+        for fil in fils_pole {
+            html += ligne(fil).as_str();
+        }
+        html += "</table>\n</section>\n";
+    }
+    html + "</body>\n</html>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stockage;
+
+    use super::*;
+
+    #[test]
+    fn regroupe_par_pole() {
+        let fils = stockage::tests::fixture("v2.yml");
+        let html = generer(&fils.iter().collect::<Vec<&Fil>>());
+        assert!(html.find("<h2>Technique (2)</h2>").unwrap() < html.find("<h2>Légal (1)</h2>").unwrap());
+        assert!(html.contains("<span class=\"statut vote\">Vote</span>"));
+        assert!(html.contains("<td>alice, carole</td>"));
+        /* L’échéance d’un fil qui n’est plus au vote n’est pas affichée. */
+        assert!(html.contains("<td>22/07/2024 00:13</td>"));
+        assert!(!html.contains("25/07/2024"));
+    }
+}
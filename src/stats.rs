@@ -0,0 +1,225 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use chrono_tz::Europe::Paris;
+use poise::ChoiceParameter;
+use serenity::all::{CreateEmbed, Timestamp};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+
+#[cfg(feature = "graphiques")]
+pub mod graphique;
+
+/// Période sur laquelle sont comptés les nouveaux fils et les changements de statut.
+#[derive(EnumIter, Clone, Copy, PartialEq, Eq, ChoiceParameter, Debug)]
+pub enum Periode {
+    #[name = "7 derniers jours"]
+    Semaine,
+    #[name = "30 derniers jours"]
+    Mois,
+    #[name = "90 derniers jours"]
+    Trimestre,
+    #[name = "365 derniers jours"]
+    Annee,
+    #[name = "Depuis le début"]
+    Tout
+}
+
+impl Periode {
+    fn debut(&self, maintenant: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let jours = match self {
+            Periode::Semaine => 7,
+            Periode::Mois => 30,
+            Periode::Trimestre => 90,
+            Periode::Annee => 365,
+            Periode::Tout => return None
+        };
+        Some(maintenant - Duration::days(jours))
+    }
+}
+
+/// Durées (en secondes) passées dans un statut avant d’en changer.
+#[derive(Default, Debug)]
+pub struct Durees(Vec<i64>);
+
+impl Durees {
+    pub fn moyenne(&self) -> Option<i64> {
+        (!self.0.is_empty()).then(|| self.0.iter().sum::<i64>() / self.0.len() as i64)
+    }
+
+    pub fn mediane(&self) -> Option<i64> {
+        let mut durees = self.0.clone();
+        durees.sort();
+        match durees.len() {
+            0 => None,
+            n if n % 2 == 0 => Some((durees[n / 2 - 1] + durees[n / 2]) / 2),
+            n => Some(durees[n / 2])
+        }
+    }
+
+    fn resume(&self) -> String {
+        match (self.moyenne(), self.mediane()) {
+            (Some(moyenne), Some(mediane)) =>
+                format!("Moyenne : {}\nMédiane : {}\n({} fil(s))", duree(moyenne), duree(mediane), self.0.len()),
+            _ => "Aucun changement sur la période.".to_string()
+        }
+    }
+}
+
+fn duree(secondes: i64) -> String {
+    let (jours, heures) = (secondes / 86400, secondes % 86400 / 3600);
+    match (jours, heures) {
+        (0, 0) => "moins d’une heure".to_string(),
+        (0, heures) => format!("{heures} h"),
+        (jours, 0) => format!("{jours} j"),
+        (jours, heures) => format!("{jours} j {heures} h")
+    }
+}
+
+fn date(timestamp: &Timestamp) -> DateTime<Utc> {
+    DateTime::from_timestamp(timestamp.unix_timestamp(), 0).unwrap_or_default()
+}
+
+/// Lundi de la semaine d’une date, à l’heure de Paris.
+fn semaine(date: DateTime<Utc>) -> NaiveDate {
+    let date = date.with_timezone(&Paris).date_naive();
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Statistiques calculées à partir de l’état et de l’historique des fils.
+#[derive(Debug)]
+pub struct Statistiques {
+    pub periode: Periode,
+    pub pole: Option<Pole>,
+    /// Nombre actuel de fils par statut.
+    pub par_statut: Vec<(Status, usize)>,
+    /// Nombre actuel de fils par pôle et par statut, pour les pôles ayant des fils.
+    pub par_pole: Vec<(Pole, Vec<(Status, usize)>)>,
+    /// Nouveaux fils par semaine (lundi de la semaine) sur la période, semaines vides comprises.
+    pub nouveaux: Vec<(NaiveDate, usize)>,
+    pub discussion_vote: Durees,
+    pub vote_termine: Durees
+}
+
+fn compter(fils: &[&Fil]) -> Vec<(Status, usize)> {
+    Status::iter().map(|statut| {
+        let nombre = fils.iter().filter(|fil| fil.status == statut).count();
+        (statut, nombre)
+    }).collect()
+}
+
+impl Statistiques {
+    pub fn calculer<'a>(fils: impl Iterator<Item = &'a Fil>, pole: Option<Pole>, periode: Periode) -> Self {
+        let maintenant = Utc::now();
+        let debut = periode.debut(maintenant);
+        let dans_periode = |date: DateTime<Utc>| debut.is_none_or(|debut| date >= debut);
+        let fils: Vec<&Fil> = fils.filter(|fil| pole.as_ref().is_none_or(|pole| fil.pole == *pole)).collect();
+
+        let par_pole = Pole::iter().filter_map(|pole| {
+            let fils_pole: Vec<&Fil> = fils.iter().copied().filter(|fil| fil.pole == pole).collect();
+            (!fils_pole.is_empty()).then(|| (pole, compter(&fils_pole)))
+        }).collect();
+
+        /* Temps passé dans chaque statut, compté seulement si l’entrée dans ce statut est connue : un changement
+         * enregistré, ou la création pour un fil ouvert en discussion. Un fil dont le premier changement part
+         * du vote a pu y passer avant l’historique : faute de preuve qu’il y a été créé, sa durée est ignorée. */
+        let mut discussion_vote = Durees::default();
+        let mut vote_termine = Durees::default();
+        for fil in &fils {
+            let mut entree = fil.creation.as_ref().map(date)
+                .filter(|_| fil.get_historique().first().is_some_and(|transition| transition.de == Status::Discussion));
+            for transition in fil.get_historique() {
+                let sortie = date(&transition.date);
+                if let Some(entree) = entree.filter(|_| dans_periode(sortie)) {
+                    match (&transition.de, &transition.vers) {
+                        (Status::Discussion, Status::Vote) => discussion_vote.0.push((sortie - entree).num_seconds()),
+                        (Status::Vote, Status::Termine) => vote_termine.0.push((sortie - entree).num_seconds()),
+                        _ => {}
+                    }
+                }
+                entree = Some(sortie);
+            }
+        }
+
+        let creations: Vec<DateTime<Utc>> = fils.iter()
+            .filter_map(|fil| fil.creation.as_ref().map(date))
+            .filter(|creation| dans_periode(*creation))
+            .collect();
+        let mut nouveaux = Vec::new();
+        if let Some(premiere) = debut.or(creations.iter().min().copied()) {
+            let mut lundi = semaine(premiere);
+            while lundi <= semaine(maintenant) {
+                nouveaux.push((lundi, creations.iter().filter(|creation| semaine(**creation) == lundi).count()));
+                lundi += Duration::weeks(1);
+            }
+        }
+
+        Self { periode, pole, par_statut: compter(&fils), par_pole, nouveaux, discussion_vote, vote_termine }
+    }
+
+    pub fn get_embed(&self) -> CreateEmbed {
+        let par_statut = self.par_statut.iter()
+            .map(|(statut, nombre)| format!("{statut} : {nombre}"))
+            .collect::<Vec<String>>().join("\n");
+        let total: usize = self.nouveaux.iter().map(|(_, nombre)| nombre).sum();
+        let mut nouveaux = format!("{total} sur la période");
+        for (lundi, nombre) in self.nouveaux.iter().rev().take(8) {
+            nouveaux += format!("\nSemaine du {} : {nombre}", lundi.format("%d/%m/%Y")).as_str();
+        }
+        let mut embed = CreateEmbed::new()
+            .title(match &self.pole {
+                Some(pole) => format!("Statistiques du pôle {pole}"),
+                None => "Statistiques du staff".to_string()
+            })
+            .description(format!("Période : {}. Les fils enregistrés sans date de création ne sont pas comptés parmi les nouveaux.",
+                                 self.periode.name().to_lowercase()))
+            .field("Fils par statut", par_statut, true)
+            .field("Nouveaux fils", nouveaux, true)
+            .field("Discussion → Vote", self.discussion_vote.resume(), false)
+            .field("Vote → Terminé", self.vote_termine.resume(), false);
+        if self.pole.is_none() {
+            for (pole, par_statut) in &self.par_pole {
+                let detail = par_statut.iter()
+                    .filter(|(_, nombre)| *nombre > 0)
+                    .map(|(statut, nombre)| format!("{statut} : {nombre}"))
+                    .collect::<Vec<String>>().join("\n");
+                embed = embed.field(pole.to_string(), detail, true);
+            }
+        }
+        embed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stockage;
+
+    use super::*;
+
+    #[test]
+    fn durees_par_statut() {
+        let fils = stockage::tests::fixture("v2.yml");
+        let stats = Statistiques::calculer(fils.iter(), None, Periode::Tout);
+        /* Discussion depuis la création (1720000000) jusqu’au vote (1721000000). */
+        assert_eq!(stats.discussion_vote.mediane(), Some(1_000_000));
+        /* La charte est terminée sans passage au vote enregistré : son entrée au vote est inconnue. */
+        assert!(stats.vote_termine.moyenne().is_none());
+        /* Le guide, passé au vote à 1721000000, l’est bien une fois terminé. */
+        let mut termines = fils.clone();
+        termines[0].set_status(Status::Termine, None);
+        let attendu = Utc::now().timestamp() - 1721000000;
+        let duree = Statistiques::calculer(termines.iter(), None, Periode::Tout).vote_termine.moyenne().unwrap();
+        assert!((duree - attendu).abs() < 60);
+        assert!(stats.par_statut.contains(&(Status::Discussion, 1)));
+        assert_eq!(stats.par_pole.len(), 2);
+        /* Le fil sans date de création n’est pas compté. */
+        assert_eq!(stats.nouveaux.iter().map(|(_, nombre)| nombre).sum::<usize>(), 2);
+
+        let technique = Statistiques::calculer(fils.iter(), Some(Pole::Technique), Periode::Tout);
+        assert_eq!(technique.par_statut.iter().map(|(_, nombre)| nombre).sum::<usize>(), 2);
+        assert!(technique.vote_termine.moyenne().is_none());
+        /* Changements trop anciens pour la dernière semaine. */
+        assert!(Statistiques::calculer(fils.iter(), None, Periode::Semaine).discussion_vote.moyenne().is_none());
+    }
+}
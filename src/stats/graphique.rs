@@ -0,0 +1,68 @@
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::sync::OnceLock;
+
+use plotters::prelude::*;
+use plotters::style::{register_font, FontStyle};
+
+use fondabots_lib::ErrType;
+
+use crate::config;
+
+use super::Statistiques;
+
+const LARGEUR: u32 = 800;
+const HAUTEUR: u32 = 400;
+
+fn erreur(e: impl Display) -> ErrType {
+    io::Error::other(e.to_string()).into()
+}
+
+/// Charge la police configurée, une seule fois : plotters n’en embarque aucune.
+fn charger_police() -> Result<(), ErrType> {
+    static POLICE: OnceLock<Result<(), String>> = OnceLock::new();
+    POLICE.get_or_init(|| {
+        let chemin = &config::get().stats.police;
+        let octets = fs::read(chemin).map_err(|e| format!("Police {chemin} illisible : {e}"))?;
+        register_font("sans-serif", FontStyle::Normal, Box::leak(octets.into_boxed_slice()))
+            .map_err(|_| format!("Police {chemin} invalide."))
+    }).clone().map_err(erreur)
+}
+
+/// Dessine l’histogramme des nouveaux fils par semaine, au format PNG.
+pub fn dessiner(stats: &Statistiques) -> Result<Vec<u8>, ErrType> {
+    charger_police()?;
+    let mut pixels = vec![0; (LARGEUR * HAUTEUR * 3) as usize];
+    {
+        let racine = BitMapBackend::with_buffer(&mut pixels, (LARGEUR, HAUTEUR)).into_drawing_area();
+        racine.fill(&WHITE).map_err(erreur)?;
+        let semaines = stats.nouveaux.len();
+        let maximum = stats.nouveaux.iter().map(|(_, nombre)| *nombre).max().unwrap_or(0).max(1);
+        let mut graphique = ChartBuilder::on(&racine)
+            .caption("Nouveaux fils par semaine", ("sans-serif", 24))
+            .margin(15)
+            .margin_right(35)
+            .x_label_area_size(30)
+            .y_label_area_size(40)
+            .build_cartesian_2d(0..semaines.max(1), 0..maximum)
+            .map_err(erreur)?;
+        graphique.configure_mesh()
+            .disable_x_mesh()
+            .x_labels(semaines.clamp(1, 12))
+            .x_label_formatter(&|i| stats.nouveaux.get(*i).map(|(lundi, _)| lundi.format("%d/%m/%y").to_string()).unwrap_or_default())
+            .draw()
+            .map_err(erreur)?;
+        graphique.draw_series(stats.nouveaux.iter().enumerate()
+            .map(|(i, (_, nombre))| Rectangle::new([(i, 0), (i + 1, *nombre)], BLUE.mix(0.7).filled())))
+            .map_err(erreur)?;
+        racine.present().map_err(erreur)?;
+    }
+
+    let mut png = Vec::new();
+    let mut encodeur = png::Encoder::new(&mut png, LARGEUR, HAUTEUR);
+    encodeur.set_color(png::ColorType::Rgb);
+    encodeur.set_depth(png::BitDepth::Eight);
+    encodeur.write_header().map_err(erreur)?.write_image_data(&pixels).map_err(erreur)?;
+    Ok(png)
+}
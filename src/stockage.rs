@@ -0,0 +1,195 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use tokio::sync::Notify;
+use yaml_rust2::{yaml, Yaml, YamlEmitter, YamlLoader};
+
+use fondabots_lib::{Bot, ErrType, Object};
+
+use crate::DataType;
+use crate::export;
+use crate::fil::Fil;
+
+pub mod sauvegardes;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// Fichier de base de données YAML, lu par fondabots-lib au lancement.
+pub const BASE: &str = "./staffbot.yml";
+
+/// Fichier où le bot enregistre la base de données : la base SQLite si elle est configurée, le fichier
+/// YAML sinon.
+pub fn base() -> &'static str {
+    #[cfg(feature = "sqlite")]
+    if let Some(chemin) = &crate::config::get().sqlite {
+        return chemin.as_str();
+    }
+    BASE
+}
+
+/// Clés du fichier de base de données, au format de fondabots-lib.
+const CLE_RELEVE: &str = "last_rss_update";
+const CLE_FILS: &str = "database";
+
+/// Base de données du bot en cours d’exécution, pour les tâches extérieures aux commandes (API HTTP…).
+static BOT: OnceLock<DataType> = OnceLock::new();
+
+/// Réveille les tâches qui attendent la base de données partagée.
+static PARTAGE: Notify = Notify::const_new();
+
+/// Rend la base de données du bot accessible via [`bot`]. Seul le premier appel a un effet.
+///
+/// fondabots-lib garde le bot jusqu’à son lancement et ne transmet la base partagée qu’ensuite : elle est
+/// partagée par `Fil::maj_rss`, que fondabots-lib appelle dès le lancement.
+pub fn partager(bot: &DataType) {
+    if BOT.set(bot.clone()).is_ok() {
+        PARTAGE.notify_waiters();
+    }
+}
+
+/// Base de données du bot, si celui-ci est lancé et l’a déjà partagée.
+pub fn bot() -> Option<DataType> {
+    BOT.get().cloned()
+}
+
+/// Attend que le bot soit lancé et ait partagé sa base de données.
+pub async fn attendre() -> DataType {
+    loop {
+        let partage = PARTAGE.notified();
+        if let Some(bot) = bot() {
+            return bot;
+        }
+        partage.await;
+    }
+}
+
+/// Écrit un fichier d’un coup : le contenu est écrit dans un fichier temporaire qui remplace ensuite
+/// la cible, pour qu’un arrêt brutal ne laisse jamais de fichier à moitié écrit.
+pub fn ecrire_atomique(chemin: &Path, contenu: &[u8]) -> Result<(), ErrType> {
+    let mut temporaire = chemin.as_os_str().to_owned();
+    temporaire.push(".tmp");
+    fs::write(&temporaire, contenu)?;
+    fs::rename(&temporaire, chemin)?;
+    Ok(())
+}
+
+/// Charge les fils d’un fichier de base de données sans passer par le bot : une base SQLite si le fichier
+/// a l’extension `.db`, un fichier YAML sinon.
+pub fn charger(chemin: &str) -> Result<Vec<Fil>, ErrType> {
+    #[cfg(feature = "sqlite")]
+    if chemin.ends_with(".db") {
+        return sqlite::lire(&sqlite::ouvrir(chemin)?);
+    }
+    charger_yaml(chemin)
+}
+
+/// Charge les fils d’un fichier de base de données YAML sans passer par le bot.
+///
+/// Le format du fichier est géré par fondabots-lib : on y récupère donc tous les nœuds
+/// ayant la forme d’un fil (c’est-à-dire possédant un champ `lien`), où qu’ils soient.
+pub fn charger_yaml(chemin: &str) -> Result<Vec<Fil>, ErrType> {
+    let contenu = fs::read_to_string(chemin)?;
+    let documents = YamlLoader::load_from_str(contenu.as_str())
+        .map_err(|e| ErrType::YamlParseError(e.to_string()))?;
+    let mut fils = Vec::new();
+    for document in &documents {
+        collecter_fils(document, &mut fils)?;
+    }
+    Ok(fils)
+}
+
+fn collecter_fils(noeud: &Yaml, fils: &mut Vec<Fil>) -> Result<(), ErrType> {
+    match noeud {
+        Yaml::Hash(hash) => {
+            if hash.contains_key(&Yaml::String("lien".to_string())) {
+                fils.push(Fil::from_yaml(noeud)?);
+            } else {
+                for valeur in hash.values() {
+                    collecter_fils(valeur, fils)?;
+                }
+            }
+        }
+        Yaml::Array(tableau) => {
+            for valeur in tableau {
+                collecter_fils(valeur, fils)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Prépare le stockage au lancement du bot, une fois la base YAML chargée par fondabots-lib.
+///
+/// Si une base SQLite est configurée, les fils en sont chargés et elle remplace le fichier YAML.
+pub fn demarrer(bot: &mut Bot<Fil>) -> Result<(), ErrType> {
+    if let Some(chemin) = &crate::config::get().sqlite {
+        #[cfg(feature = "sqlite")]
+        sqlite::demarrer(chemin, bot)?;
+        #[cfg(not(feature = "sqlite"))]
+        tracing::warn!(base = chemin, "Base SQLite configurée mais bot compilé sans la fonctionnalité « sqlite » : seul le YAML est utilisé.");
+    }
+    crate::audit::initialiser(&bot.database);
+    crate::flux::ecrire_fichiers(bot.database.values());
+    Ok(())
+}
+
+/// Document YAML de la base de données, au format de fondabots-lib : date du dernier relevé RSS et fils.
+fn document(releve: i64, fils: Vec<Yaml>) -> Result<String, ErrType> {
+    let mut document = yaml::Hash::new();
+    document.insert(Yaml::String(CLE_RELEVE.to_string()), Yaml::Integer(releve));
+    document.insert(Yaml::String(CLE_FILS.to_string()), Yaml::Array(fils));
+    let mut contenu = String::new();
+    YamlEmitter::new(&mut contenu).dump(&Yaml::Hash(document))
+        .map_err(|e| io::Error::other(format!("Erreur lors de l’écriture de la base de données : {e}")))?;
+    Ok(contenu + "\n")
+}
+
+/// Contenu du fichier de base de données, avec les fils triés pour que le fichier ne change pas d’un
+/// enregistrement à l’autre sans raison.
+fn serialiser(bot: &Bot<Fil>) -> Result<String, ErrType> {
+    document(bot.last_rss_update.timestamp(), export::trier(bot.database.values()).into_iter().map(Fil::serialize).collect())
+}
+
+/// Sauvegarde la base telle qu’elle a été enregistrée pour la dernière fois : copie du fichier YAML, ou
+/// contenu de la base SQLite au format YAML.
+pub fn sauvegarder(forcer: bool) -> Result<Option<sauvegardes::Sauvegarde>, ErrType> {
+    sauvegardes::sauvegarder(forcer, || {
+        #[cfg(feature = "sqlite")]
+        if let Some(contenu) = sqlite::instantane()? {
+            return Ok(Some(contenu.into_bytes()));
+        }
+        Ok(if Path::new(BASE).exists() { Some(fs::read(BASE)?) } else { None })
+    })
+}
+
+/// Enregistre la base de données, après une sauvegarde de l’état précédent si la dernière est trop
+/// ancienne : seuls les fils modifiés sont écrits dans la base SQLite si elle est configurée, le fichier
+/// YAML est sinon réécrit d’un coup.
+pub fn enregistrer(bot: &mut Bot<Fil>) -> Result<(), ErrType> {
+    if let Err(e) = sauvegarder(false) {
+        tracing::error!(erreur = %e, "Erreur lors de la sauvegarde de la base de données");
+    }
+    #[cfg(feature = "sqlite")]
+    if sqlite::enregistrer(bot)? {
+        return Ok(());
+    }
+    ecrire_atomique(Path::new(BASE), serialiser(bot)?.as_bytes())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Chemin d’un fichier de `tests/fixtures`.
+    pub fn chemin_fixture(nom: &str) -> String {
+        format!("{}/tests/fixtures/{nom}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    /// Fils d’une base de test de `tests/fixtures`, dans l’ordre du fichier.
+    pub fn fixture(nom: &str) -> Vec<Fil> {
+        charger_yaml(chemin_fixture(nom).as_str()).unwrap()
+    }
+}
@@ -0,0 +1,192 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use yaml_rust2::YamlLoader;
+
+use fondabots_lib::{ErrType, Object};
+
+use crate::config;
+use crate::fil::Fil;
+
+use super::ecrire_atomique;
+
+const PREFIXE: &str = "staffbot-";
+const FORMAT_DATE: &str = "%Y%m%d-%H%M%S";
+
+/// Copie horodatée du fichier de base de données.
+pub struct Sauvegarde {
+    pub nom: String,
+    pub chemin: PathBuf,
+    pub date: DateTime<Utc>,
+    pub taille: u64
+}
+
+impl Sauvegarde {
+    fn depuis_chemin(chemin: PathBuf) -> Option<Self> {
+        let nom = chemin.file_name()?.to_str()?.to_string();
+        let date = NaiveDateTime::parse_from_str(nom.strip_prefix(PREFIXE)?.strip_suffix(".yml")?, FORMAT_DATE).ok()?.and_utc();
+        let taille = fs::metadata(&chemin).ok()?.len();
+        Some(Self { nom, chemin, date, taille })
+    }
+}
+
+/// Liste les sauvegardes existantes, de la plus récente à la plus ancienne.
+pub fn lister() -> Result<Vec<Sauvegarde>, ErrType> {
+    lister_dans(&config::get().sauvegardes.dossier)
+}
+
+fn lister_dans(dossier: &str) -> Result<Vec<Sauvegarde>, ErrType> {
+    if !Path::new(dossier).exists() {
+        return Ok(Vec::new());
+    }
+    let mut sauvegardes: Vec<Sauvegarde> = fs::read_dir(dossier)?
+        .filter_map(|entree| entree.ok())
+        .filter_map(|entree| Sauvegarde::depuis_chemin(entree.path()))
+        .collect();
+    sauvegardes.sort_by_key(|sauvegarde| Reverse(sauvegarde.date));
+    Ok(sauvegardes)
+}
+
+pub fn trouver(nom: &str) -> Result<Sauvegarde, ErrType> {
+    lister()?.into_iter().find(|sauvegarde| sauvegarde.nom == nom)
+        .ok_or(ErrType::ObjectNotFound(format!("Sauvegarde {nom} inexistante.")))
+}
+
+/// Écrit une sauvegarde de `contenu` (rien s’il n’y a pas encore de base) dans le dossier des sauvegardes,
+/// puis supprime les plus anciennes au-delà de la limite configurée.
+///
+/// Sans `forcer`, rien n’est fait si la dernière sauvegarde est plus récente que l’intervalle configuré.
+pub fn sauvegarder(forcer: bool, contenu: impl FnOnce() -> Result<Option<Vec<u8>>, ErrType>) -> Result<Option<Sauvegarde>, ErrType> {
+    sauvegarder_selon(&config::get().sauvegardes, forcer, contenu)
+}
+
+fn sauvegarder_selon(config: &config::Sauvegardes, forcer: bool, contenu: impl FnOnce() -> Result<Option<Vec<u8>>, ErrType>) -> Result<Option<Sauvegarde>, ErrType> {
+    if config.conservation == 0 {
+        return Ok(None);
+    }
+    let maintenant = Utc::now();
+    if !forcer && lister_dans(&config.dossier)?.first()
+        .is_some_and(|derniere| (maintenant - derniere.date).num_minutes() < config.intervalle as i64) {
+        return Ok(None);
+    }
+    let Some(contenu) = contenu()? else {
+        return Ok(None);
+    };
+
+    fs::create_dir_all(&config.dossier)?;
+    let chemin = Path::new(&config.dossier).join(format!("{PREFIXE}{}.yml", maintenant.format(FORMAT_DATE)));
+    ecrire_atomique(&chemin, contenu.as_slice())?;
+    for ancienne in lister_dans(&config.dossier)?.into_iter().skip(config.conservation) {
+        fs::remove_file(ancienne.chemin)?;
+    }
+    Ok(Sauvegarde::depuis_chemin(chemin))
+}
+
+/// Au démarrage, remplace le fichier de base de données par la dernière sauvegarde lisible
+/// s’il est vide ou illisible (par exemple après un arrêt brutal en pleine écriture).
+pub fn recuperer(base: &str) -> Result<(), ErrType> {
+    recuperer_depuis(base, &config::get().sauvegardes.dossier)
+}
+
+fn recuperer_depuis(base: &str, dossier: &str) -> Result<(), ErrType> {
+    let lisible = |chemin: &Path| fs::read_to_string(chemin).ok()
+        .is_some_and(|contenu| !contenu.trim().is_empty() && YamlLoader::load_from_str(contenu.as_str()).is_ok());
+    if !Path::new(base).exists() || lisible(Path::new(base)) {
+        return Ok(());
+    }
+    match lister_dans(dossier)?.into_iter().find(|sauvegarde| lisible(&sauvegarde.chemin)) {
+        Some(sauvegarde) => {
+            tracing::warn!(base, sauvegarde = sauvegarde.nom, "Base de données illisible, restauration de la dernière sauvegarde lisible.");
+            fs::rename(base, format!("{base}.corrompu"))?;
+            ecrire_atomique(Path::new(base), fs::read(&sauvegarde.chemin)?.as_slice())
+        }
+        None => {
+            tracing::error!(base, "Base de données illisible et aucune sauvegarde lisible disponible.");
+            Ok(())
+        }
+    }
+}
+
+/// Décrit les différences entre la base actuelle et le contenu d’une sauvegarde.
+pub fn comparer(actuels: &HashMap<u64, Fil>, sauvegarde: &[Fil]) -> Vec<String> {
+    let mut differences = Vec::new();
+    for fil in sauvegarde {
+        match actuels.get(&fil.get_id()) {
+            None => differences.push(format!("+ « {} » ({}) serait rétabli", fil.get_name(), fil.get_id())),
+            Some(actuel) => {
+                let mut changements = Vec::new();
+                if actuel.get_name() != fil.get_name() {
+                    changements.push(format!("nom « {} » → « {} »", actuel.get_name(), fil.get_name()));
+                }
+                if actuel.pole != fil.pole {
+                    changements.push(format!("pôle {} → {}", actuel.pole, fil.pole));
+                }
+                if actuel.status != fil.status {
+                    changements.push(format!("statut {} → {}", actuel.status, fil.status));
+                }
+                if !changements.is_empty() {
+                    differences.push(format!("~ « {} » ({}) : {}", actuel.get_name(), fil.get_id(), changements.join(", ")));
+                }
+            }
+        }
+    }
+    for actuel in actuels.values() {
+        if !sauvegarde.iter().any(|fil| fil.get_id() == actuel.get_id()) {
+            differences.push(format!("- « {} » ({}) serait supprimé", actuel.get_name(), actuel.get_id()));
+        }
+    }
+    differences.sort();
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dossier(nom: &str) -> PathBuf {
+        let dossier = std::env::temp_dir().join(format!("staffbot-{nom}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dossier);
+        fs::create_dir_all(&dossier).unwrap();
+        dossier
+    }
+
+    #[test]
+    fn conserve_les_plus_recentes() {
+        let dossier = dossier("rotation");
+        let config = config::Sauvegardes { dossier: dossier.to_string_lossy().to_string(), conservation: 2, intervalle: 60 };
+        for nom in ["staffbot-20240101-000000.yml", "staffbot-20240102-000000.yml", "autre.yml"] {
+            fs::write(dossier.join(nom), "---\n").unwrap();
+        }
+
+        let sauvegarde = sauvegarder_selon(&config, false, || Ok(Some(b"contenu".to_vec()))).unwrap().unwrap();
+        assert_eq!(fs::read(&sauvegarde.chemin).unwrap(), b"contenu");
+        let noms: Vec<String> = lister_dans(&config.dossier).unwrap().into_iter().map(|sauvegarde| sauvegarde.nom).collect();
+        assert_eq!(noms, vec![sauvegarde.nom.clone(), "staffbot-20240102-000000.yml".to_string()]);
+        /* Les autres fichiers du dossier ne sont pas touchés. */
+        assert!(dossier.join("autre.yml").exists());
+
+        /* La dernière sauvegarde est trop récente, sauf à forcer ; sans base, rien n’est sauvegardé. */
+        assert!(sauvegarder_selon(&config, false, || panic!("contenu lu inutilement")).unwrap().is_none());
+        assert!(sauvegarder_selon(&config, true, || Ok(None)).unwrap().is_none());
+        fs::remove_dir_all(dossier).unwrap();
+    }
+
+    #[test]
+    fn recupere_une_base_illisible() {
+        let dossier = dossier("recuperation");
+        let base = dossier.join("staffbot.yml");
+        fs::write(dossier.join("staffbot-20240101-000000.yml"), "database: []\n").unwrap();
+        fs::write(dossier.join("staffbot-20240102-000000.yml"), "").unwrap();
+        fs::write(&base, "database: [\n").unwrap();
+
+        recuperer_depuis(base.to_str().unwrap(), dossier.to_str().unwrap()).unwrap();
+        /* La sauvegarde vide est ignorée au profit de la précédente, et la base illisible est gardée à part. */
+        assert_eq!(fs::read_to_string(&base).unwrap(), "database: []\n");
+        assert_eq!(fs::read_to_string(dossier.join("staffbot.yml.corrompu")).unwrap(), "database: [\n");
+        assert!(!dossier.join("staffbot.yml.tmp").exists());
+        fs::remove_dir_all(dossier).unwrap();
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader, yaml};
+
+use fondabots_lib::{Bot, ErrType, Object};
+
+use crate::fil::Fil;
+
+/// Base ouverte au démarrage du bot, qui remplace alors le fichier YAML pour les enregistrements.
+static BASE: Mutex<Option<Base>> = Mutex::new(None);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS fils (
+    id INTEGER PRIMARY KEY,
+    nom TEXT NOT NULL,
+    lien TEXT NOT NULL,
+    pole TEXT NOT NULL,
+    status TEXT NOT NULL,
+    last_update INTEGER NOT NULL,
+    edited INTEGER NOT NULL,
+    autres TEXT NOT NULL DEFAULT ''
+);
+CREATE TABLE IF NOT EXISTS historique (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fil INTEGER NOT NULL REFERENCES fils(id) ON DELETE CASCADE,
+    de TEXT NOT NULL,
+    vers TEXT NOT NULL,
+    date INTEGER NOT NULL,
+    auteur TEXT,
+    nom TEXT,
+    pole TEXT
+);
+CREATE TABLE IF NOT EXISTS notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fil INTEGER NOT NULL REFERENCES fils(id) ON DELETE CASCADE,
+    auteur TEXT NOT NULL,
+    date INTEGER NOT NULL,
+    texte TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS meta (
+    cle TEXT PRIMARY KEY,
+    valeur INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS historique_fil ON historique(fil);
+CREATE INDEX IF NOT EXISTS notes_fil ON notes(fil);
+PRAGMA foreign_keys = ON;
+";
+
+/// Champs du fil stockés dans leur propre colonne ou table ; les autres sont gardés en YAML dans `autres`.
+const CHAMPS_DEDIES: [&str; 8] = ["nom", "lien", "pole", "status", "lastUpdate", "edited", "historique", "notes"];
+
+fn erreur(e: impl ToString) -> ErrType {
+    io::Error::other(format!("Erreur SQLite : {}", e.to_string())).into()
+}
+
+fn cle(s: &str) -> Yaml {
+    Yaml::String(s.to_string())
+}
+
+pub fn ouvrir(chemin: &str) -> Result<Connection, ErrType> {
+    let connexion = Connection::open(chemin).map_err(erreur)?;
+    connexion.execute_batch(SCHEMA).map_err(erreur)?;
+    /* Colonnes ajoutées après la création des premières bases. */
+    let colonnes: Vec<String> = connexion.prepare("SELECT name FROM pragma_table_info('historique')").map_err(erreur)?
+        .query_map([], |ligne| ligne.get(0)).map_err(erreur)?
+        .collect::<Result<_, _>>().map_err(erreur)?;
+    for colonne in ["nom", "pole"] {
+        if !colonnes.iter().any(|autre| autre == colonne) {
+            connexion.execute(format!("ALTER TABLE historique ADD COLUMN {colonne} TEXT").as_str(), []).map_err(erreur)?;
+        }
+    }
+    Ok(connexion)
+}
+
+/// Écrit un fil dans la base SQLite, en remplaçant sa version précédente et son historique.
+///
+/// Le fil passe par `Fil::serialize`, pour que la base SQLite et le YAML restent équivalents.
+fn ecrire_fil(transaction: &Transaction, id: u64, data: &Yaml) -> Result<(), ErrType> {
+    let Yaml::Hash(data) = data else {
+        return Err(ErrType::YamlParseError(format!("Sérialisation inattendue du fil {id}.")));
+    };
+    let mut autres = yaml::Hash::new();
+    for (k, v) in data.iter() {
+        if !k.as_str().is_some_and(|k| CHAMPS_DEDIES.contains(&k)) {
+            autres.insert(k.clone(), v.clone());
+        }
+    }
+    let mut autres_yaml = String::new();
+    if !autres.is_empty() {
+        YamlEmitter::new(&mut autres_yaml).dump(&Yaml::Hash(autres)).map_err(erreur)?;
+    }
+
+    transaction.execute(
+        "INSERT INTO fils (id, nom, lien, pole, status, last_update, edited, autres) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(id) DO UPDATE SET nom = ?2, lien = ?3, pole = ?4, status = ?5, last_update = ?6, edited = ?7, autres = ?8",
+        params![
+            id as i64,
+            data[&cle("nom")].as_str(),
+            data[&cle("lien")].as_str(),
+            data[&cle("pole")].as_str(),
+            data[&cle("status")].as_str(),
+            data[&cle("lastUpdate")].as_i64(),
+            data[&cle("edited")].as_bool().unwrap_or(false),
+            autres_yaml
+        ]).map_err(erreur)?;
+    transaction.execute("DELETE FROM historique WHERE fil = ?1", params![id as i64]).map_err(erreur)?;
+    transaction.execute("DELETE FROM notes WHERE fil = ?1", params![id as i64]).map_err(erreur)?;
+    for transition in data.get(&cle("historique")).and_then(Yaml::as_vec).into_iter().flatten() {
+        transaction.execute(
+            "INSERT INTO historique (fil, de, vers, date, auteur, nom, pole) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id as i64, transition["de"].as_str(), transition["vers"].as_str(),
+                transition["date"].as_i64(), transition["auteur"].as_str(),
+                transition["nom"].as_str(), transition["pole"].as_str()]).map_err(erreur)?;
+    }
+    for note in data.get(&cle("notes")).and_then(Yaml::as_vec).into_iter().flatten() {
+        transaction.execute(
+            "INSERT INTO notes (fil, auteur, date, texte) VALUES (?1, ?2, ?3, ?4)",
+            params![id as i64, note["auteur"].as_str(), note["date"].as_i64(), note["texte"].as_str()]).map_err(erreur)?;
+    }
+    Ok(())
+}
+
+/// Base SQLite ouverte, avec la sérialisation de chaque fil et la date du dernier relevé telles qu’elles y
+/// sont écrites.
+struct Base {
+    connexion: Connection,
+    ecrits: HashMap<u64, Yaml>,
+    /// Absente tant que la base n’a jamais été remplie.
+    releve: Option<i64>
+}
+
+impl Base {
+    fn ouvrir(chemin: &str) -> Result<(Self, Vec<Fil>), ErrType> {
+        let connexion = ouvrir(chemin)?;
+        let fils = lire(&connexion)?;
+        let releve = connexion.query_row("SELECT valeur FROM meta WHERE cle = ?1", params![super::CLE_RELEVE], |ligne| ligne.get(0))
+            .optional().map_err(erreur)?;
+        let ecrits = fils.iter().map(|fil| (fil.get_id(), fil.serialize())).collect();
+        Ok((Self { connexion, ecrits, releve }, fils))
+    }
+
+    /// Met la base en accord avec les fils donnés, en une seule transaction : seuls les fils ajoutés,
+    /// modifiés ou supprimés depuis la dernière écriture sont touchés. Renvoie le nombre de fils touchés.
+    fn synchroniser(&mut self, database: &HashMap<u64, Fil>, releve: i64) -> Result<usize, ErrType> {
+        let modifies: Vec<(u64, Yaml)> = database.values()
+            .map(|fil| (fil.get_id(), fil.serialize()))
+            .filter(|(id, data)| self.ecrits.get(id) != Some(data))
+            .collect();
+        let supprimes: Vec<u64> = self.ecrits.keys().filter(|id| !database.contains_key(id)).copied().collect();
+        if modifies.is_empty() && supprimes.is_empty() && self.releve == Some(releve) {
+            return Ok(0);
+        }
+
+        let transaction = self.connexion.transaction().map_err(erreur)?;
+        for (id, data) in &modifies {
+            ecrire_fil(&transaction, *id, data)?;
+        }
+        for id in &supprimes {
+            transaction.execute("DELETE FROM fils WHERE id = ?1", params![*id as i64]).map_err(erreur)?;
+        }
+        transaction.execute("INSERT INTO meta (cle, valeur) VALUES (?1, ?2) ON CONFLICT(cle) DO UPDATE SET valeur = ?2",
+                            params![super::CLE_RELEVE, releve]).map_err(erreur)?;
+        transaction.commit().map_err(erreur)?;
+
+        let touches = modifies.len() + supprimes.len();
+        for id in supprimes {
+            self.ecrits.remove(&id);
+        }
+        self.ecrits.extend(modifies);
+        self.releve = Some(releve);
+        Ok(touches)
+    }
+
+    /// Contenu de la base tel qu’il a été écrit, au format du fichier YAML.
+    fn instantane(&self) -> Result<String, ErrType> {
+        let mut ids: Vec<&u64> = self.ecrits.keys().collect();
+        ids.sort();
+        super::document(self.releve.unwrap_or_default(), ids.into_iter().map(|id| self.ecrits[id].clone()).collect())
+    }
+}
+
+/// Lit tous les fils de la base SQLite, en reconstituant leur forme YAML pour passer par `Fil::from_yaml`.
+pub fn lire(connexion: &Connection) -> Result<Vec<Fil>, ErrType> {
+    let mut historiques: HashMap<i64, Vec<Yaml>> = HashMap::new();
+    let mut requete = connexion.prepare("SELECT fil, de, vers, date, auteur, nom, pole FROM historique ORDER BY id").map_err(erreur)?;
+    let mut lignes = requete.query([]).map_err(erreur)?;
+    while let Some(ligne) = lignes.next().map_err(erreur)? {
+        let mut transition = yaml::Hash::new();
+        transition.insert(cle("de"), Yaml::String(ligne.get(1).map_err(erreur)?));
+        transition.insert(cle("vers"), Yaml::String(ligne.get(2).map_err(erreur)?));
+        transition.insert(cle("date"), Yaml::Integer(ligne.get(3).map_err(erreur)?));
+        for (i, champ) in [(4, "auteur"), (5, "nom"), (6, "pole")] {
+            if let Some(valeur) = ligne.get::<_, Option<String>>(i).map_err(erreur)? {
+                transition.insert(cle(champ), Yaml::String(valeur));
+            }
+        }
+        historiques.entry(ligne.get(0).map_err(erreur)?).or_default().push(Yaml::Hash(transition));
+    }
+
+    let mut notes: HashMap<i64, Vec<Yaml>> = HashMap::new();
+    let mut requete = connexion.prepare("SELECT fil, auteur, date, texte FROM notes ORDER BY id").map_err(erreur)?;
+    let mut lignes = requete.query([]).map_err(erreur)?;
+    while let Some(ligne) = lignes.next().map_err(erreur)? {
+        let mut note = yaml::Hash::new();
+        note.insert(cle("auteur"), Yaml::String(ligne.get(1).map_err(erreur)?));
+        note.insert(cle("date"), Yaml::Integer(ligne.get(2).map_err(erreur)?));
+        note.insert(cle("texte"), Yaml::String(ligne.get(3).map_err(erreur)?));
+        notes.entry(ligne.get(0).map_err(erreur)?).or_default().push(Yaml::Hash(note));
+    }
+
+    let mut fils = Vec::new();
+    let mut requete = connexion.prepare("SELECT id, nom, lien, pole, status, last_update, edited, autres FROM fils").map_err(erreur)?;
+    let mut lignes = requete.query([]).map_err(erreur)?;
+    while let Some(ligne) = lignes.next().map_err(erreur)? {
+        let id: i64 = ligne.get(0).map_err(erreur)?;
+        let autres: String = ligne.get(7).map_err(erreur)?;
+        let mut data = match YamlLoader::load_from_str(autres.as_str()).map_err(erreur)?.into_iter().next() {
+            Some(Yaml::Hash(hash)) => hash,
+            _ => yaml::Hash::new()
+        };
+        data.insert(cle("nom"), Yaml::String(ligne.get(1).map_err(erreur)?));
+        data.insert(cle("lien"), Yaml::String(ligne.get(2).map_err(erreur)?));
+        data.insert(cle("pole"), Yaml::String(ligne.get(3).map_err(erreur)?));
+        data.insert(cle("status"), Yaml::String(ligne.get(4).map_err(erreur)?));
+        data.insert(cle("lastUpdate"), Yaml::Integer(ligne.get(5).map_err(erreur)?));
+        data.insert(cle("edited"), Yaml::Boolean(ligne.get(6).map_err(erreur)?));
+        data.insert(cle("historique"), Yaml::Array(historiques.remove(&id).unwrap_or_default()));
+        data.insert(cle("notes"), Yaml::Array(notes.remove(&id).unwrap_or_default()));
+        fils.push(Fil::from_yaml(&Yaml::Hash(data))?);
+    }
+    Ok(fils)
+}
+
+/// Ouvre la base SQLite au lancement du bot et y charge les fils et la date du dernier relevé, à la place
+/// de ceux lus par fondabots-lib dans le fichier YAML. Une base neuve, ou une ancienne copie jamais remplie
+/// comme base à part entière, est d’abord remplie à partir du YAML.
+pub fn demarrer(chemin: &str, bot: &mut Bot<Fil>) -> Result<(), ErrType> {
+    let (mut base, fils) = Base::ouvrir(chemin)?;
+    match base.releve.and_then(|releve| DateTime::<Utc>::from_timestamp(releve, 0)) {
+        Some(releve) => {
+            bot.database = fils.into_iter().map(|fil| (fil.get_id(), fil)).collect();
+            bot.last_rss_update = releve;
+            tracing::info!(base = chemin, fils = bot.database.len(), "Base de données chargée depuis SQLite");
+        }
+        None => {
+            base.synchroniser(&bot.database, bot.last_rss_update.timestamp())?;
+            tracing::info!(base = chemin, fils = bot.database.len(), "Base SQLite remplie à partir de la base YAML");
+        }
+    }
+    *BASE.lock().unwrap() = Some(base);
+    Ok(())
+}
+
+/// Enregistre les fils modifiés depuis la dernière écriture et la date du dernier relevé. Renvoie faux si
+/// la base SQLite n’a pas été ouverte au démarrage : le fichier YAML reste alors utilisé.
+pub fn enregistrer(bot: &Bot<Fil>) -> Result<bool, ErrType> {
+    match BASE.lock().unwrap().as_mut() {
+        Some(base) => base.synchroniser(&bot.database, bot.last_rss_update.timestamp()).map(|_| true),
+        None => Ok(false)
+    }
+}
+
+/// Contenu de la base SQLite ouverte au démarrage, au format du fichier YAML, pour les sauvegardes.
+pub fn instantane() -> Result<Option<String>, ErrType> {
+    BASE.lock().unwrap().as_ref().map(Base::instantane).transpose()
+}
+
+/// Migration ponctuelle d’un fichier YAML vers une nouvelle base SQLite.
+pub fn migrer(yaml: &str, sqlite: &str) -> Result<usize, ErrType> {
+    if Path::new(sqlite).exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("La base {sqlite} existe déjà.")).into());
+    }
+    let fils: HashMap<u64, Fil> = super::charger_yaml(yaml)?.into_iter().map(|fil| (fil.get_id(), fil)).collect();
+    let documents = YamlLoader::load_from_str(fs::read_to_string(yaml)?.as_str()).map_err(erreur)?;
+    let releve = documents.first().and_then(|document| document[super::CLE_RELEVE].as_i64())
+        .unwrap_or(Utc::now().timestamp());
+    Base::ouvrir(sqlite)?.0.synchroniser(&fils, releve)?;
+    Ok(fils.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enregistre_les_fils_modifies() {
+        let chemin = std::env::temp_dir().join(format!("staffbot-{}.db", std::process::id()));
+        let chemin = chemin.to_str().unwrap();
+        let mut database: HashMap<u64, Fil> = crate::stockage::tests::fixture("v2.yml").into_iter()
+            .map(|fil| (fil.get_id(), fil)).collect();
+
+        let (mut base, fils) = Base::ouvrir(chemin).unwrap();
+        assert!(fils.is_empty() && base.releve.is_none());
+        assert_eq!(base.synchroniser(&database, 1722000000).unwrap(), 3);
+        database.get_mut(&1234567).unwrap().set_name("Guide refondu".to_string());
+        database.remove(&1111111);
+        assert_eq!(base.synchroniser(&database, 1722000000).unwrap(), 2);
+        assert_eq!(base.synchroniser(&database, 1722000000).unwrap(), 0);
+        assert!(base.instantane().unwrap().starts_with("---\nlast_rss_update: 1722000000\n"));
+        drop(base);
+
+        let (base, mut fils) = Base::ouvrir(chemin).unwrap();
+        fs::remove_file(chemin).unwrap();
+        assert_eq!(base.releve, Some(1722000000));
+        fils.sort_by_key(|fil| fil.get_id());
+        let mut attendus: Vec<Fil> = database.into_values().collect();
+        attendus.sort_by_key(|fil| fil.get_id());
+        assert_eq!(fils, attendus);
+    }
+}
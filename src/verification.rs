@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use reqwest::{Client, StatusCode};
+use rss::Channel;
+
+use fondabots_lib::{DataType, ErrType, Object};
+
+use crate::fil::fields::{Pole, Status};
+use crate::fil::Fil;
+use crate::{audit, config, entete, liens, stockage};
+
+/// Acteur des changements faits par la vérification, dans l’audit et l’historique des fils.
+const ACTEUR: &str = "Vérification du forum";
+
+/// Délai maximal de chaque requête au forum.
+const DELAI: Duration = Duration::from_secs(10);
+
+/// État d’un fil sur le forum.
+#[derive(Debug, PartialEq)]
+enum Constat {
+    /// Le fil existe, dans la catégorie du pôle indiqué si elle est suivie.
+    Present { pole: Option<Pole>, reponses: Option<u32> },
+    /// Le forum ne connaît plus le fil.
+    Disparu
+}
+
+fn disparu(statut: StatusCode) -> bool {
+    statut == StatusCode::NOT_FOUND || statut == StatusCode::GONE
+}
+
+/// Consulte la page et le flux d’un fil. Seule une réponse « introuvable » du forum fait conclure à sa
+/// disparition : une erreur de réseau ou du serveur ne permet pas de conclure.
+async fn constater(client: &Client, fil: &Fil) -> Result<Constat, ErrType> {
+    let page = client.get(fil.get_lien()).send().await?;
+    if disparu(page.status()) {
+        return Ok(Constat::Disparu);
+    }
+    let page = page.error_for_status()?.text().await?;
+    let mut reponses = None;
+    if let Some(flux) = fil.flux() {
+        let reponse = client.get(flux.as_str()).send().await?;
+        if disparu(reponse.status()) {
+            return Ok(Constat::Disparu);
+        }
+        match async { Ok::<_, ErrType>(Channel::read_from(&reponse.error_for_status()?.bytes().await?[..])?) }.await {
+            Ok(rss) => reponses = Some(liens::reponses(&rss)),
+            Err(e) => tracing::debug!(erreur = %e, flux, "Flux du fil illisible")
+        }
+    }
+    Ok(Constat::Present { pole: liens::pole_depuis_page(page.as_str()), reponses })
+}
+
+async fn constater_tous(client: &Client, fils: &[Fil], pause: Duration) -> Vec<(u64, Constat)> {
+    let mut constats = Vec::new();
+    for fil in fils {
+        match constater(client, fil).await {
+            Ok(constat) => constats.push((fil.get_id(), constat)),
+            Err(e) => tracing::warn!(erreur = %e, fil = fil.get_id(), "Fil non vérifié, forum inaccessible")
+        }
+        tokio::time::sleep(pause).await;
+    }
+    constats
+}
+
+/// Le constat change-t-il le pôle ou le statut du fil ?
+fn modifie(fil: &Fil, constat: &Constat) -> bool {
+    match constat {
+        Constat::Present { pole, .. } => pole.as_ref().is_some_and(|pole| *pole != fil.pole),
+        Constat::Disparu => fil.status != Status::Termine
+    }
+}
+
+/// Déplace le fil dans le pôle de sa nouvelle catégorie, ou le termine avec une note s’il a disparu.
+fn appliquer(fil: &mut Fil, constat: Constat) {
+    match constat {
+        Constat::Present { pole, reponses } => {
+            if let Some(pole) = pole.filter(|pole| *pole != fil.pole) {
+                fil.pole = pole;
+                fil.set_modified(true);
+            }
+            if reponses.is_some() && reponses != fil.reponses {
+                fil.reponses = reponses;
+                fil.set_modified(true);
+            }
+        }
+        Constat::Disparu => if fil.status != Status::Termine {
+            fil.ajouter_note(ACTEUR.to_string(), "Fil introuvable sur le forum (supprimé ?) : marqué comme terminé.".to_string());
+            fil.set_status(Status::Termine, Some(ACTEUR.to_string()));
+            fil.set_modified(true);
+        }
+    }
+}
+
+/// Vérifie sur le forum chaque fil non terminé et répercute les déplacements et disparitions.
+///
+/// Les requêtes se font sans verrouiller la base ; les constats s’appliquent ensuite aux fils qui
+/// existent toujours, avec archivage pour `/annuler` et audit des changements.
+pub async fn verifier(bot: &DataType<Fil>) {
+    let fils: Vec<Fil> = bot.lock().await.database.values().filter(|fil| fil.status != Status::Termine).cloned().collect();
+    let client = match Client::builder().timeout(DELAI).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!(erreur = %e, "Client HTTP indisponible pour vérifier les fils");
+            return;
+        }
+    };
+    let constats = constater_tous(&client, &fils, Duration::from_millis(config::get().verification.pause)).await;
+
+    let bot = &mut bot.lock().await;
+    let modifies: Vec<u64> = constats.iter()
+        .filter(|(id, constat)| bot.database.get(id).is_some_and(|fil| modifie(fil, constat)))
+        .map(|(id, _)| *id).collect();
+    let modification = audit::Modification::commencer(&bot.database, constats.iter().map(|(id, _)| *id), ACTEUR);
+    if !modifies.is_empty() {
+        bot.archive(modifies.clone());
+    }
+    for (id, constat) in constats {
+        if let Some(fil) = bot.database.get_mut(&id) {
+            appliquer(fil, constat);
+        }
+    }
+    modification.terminer(&bot.database);
+    tracing::info!(fils = fils.len(), modifies = modifies.len(), "Vérification des fils sur le forum terminée");
+    entete::signaler(bot);
+    if let Err(e) = stockage::enregistrer(bot) {
+        tracing::error!(erreur = %e, "Erreur lors de l’enregistrement de la base de données");
+    }
+}
+
+/// Vérifie les fils dès le lancement du bot, puis à l’intervalle configuré.
+pub async fn planifier() {
+    let intervalle = config::get().verification.intervalle;
+    if intervalle == 0 {
+        return;
+    }
+    let bot = stockage::attendre().await;
+    loop {
+        verifier(&bot).await;
+        tokio::time::sleep(Duration::from_secs(intervalle * 3600)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use axum::http::header;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn flux(messages: usize) -> ([(header::HeaderName, &'static str); 1], String) {
+        let items: String = (0..messages).map(|i| format!("<item><title>Message {i}</title></item>")).collect();
+        ([(header::CONTENT_TYPE, "application/rss+xml")],
+         format!("<?xml version=\"1.0\"?><rss version=\"2.0\"><channel><title>Fil</title><link>l</link><description>d</description>{items}</channel></rss>"))
+    }
+
+    /// Forum local : un fil resté en Technique, un fil déplacé en Légal, les autres introuvables.
+    async fn forum() -> String {
+        let routeur = Router::new()
+            .route("/forum/t-1/reste", get(|| async { fs::read_to_string(format!("{}/tests/fixtures/page-fil.html", env!("CARGO_MANIFEST_DIR"))).unwrap() }))
+            .route("/feed/forum/t-1.xml", get(|| async { flux(3) }))
+            .route("/forum/t-3/deplace", get(|| async { "<div class=\"forum-breadcrumbs\"><a href=\"/forum/c-1905799/legal\">Légal</a></div>" }))
+            .route("/feed/forum/t-3.xml", get(|| async { flux(1) }));
+        let ecoute = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let adresse = ecoute.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(ecoute, routeur).await.unwrap() });
+        format!("http://{adresse}")
+    }
+
+    #[tokio::test]
+    async fn repere_deplacements_et_disparitions() {
+        let site = forum().await;
+        let fil = |id: u64, nom: &str| Fil::new(nom.to_string(), format!("{site}/forum/t-{id}/{nom}"), Pole::Technique, Status::Discussion);
+        let mut fils = vec![fil(1, "reste"), fil(2, "supprime"), fil(3, "deplace")];
+        let constats = constater_tous(&Client::new(), &fils, Duration::ZERO).await;
+        assert_eq!(constats, vec![
+            (1, Constat::Present { pole: Some(Pole::Technique), reponses: Some(2) }),
+            (2, Constat::Disparu),
+            (3, Constat::Present { pole: Some(Pole::Legal), reponses: Some(0) })
+        ]);
+
+        let modifies: Vec<bool> = fils.iter().zip(&constats).map(|(fil, (_, constat))| modifie(fil, constat)).collect();
+        assert_eq!(modifies, vec![false, true, true]);
+        for (fil, (_, constat)) in fils.iter_mut().zip(constats) {
+            appliquer(fil, constat);
+        }
+        assert_eq!((fils[0].pole.clone(), fils[0].reponses), (Pole::Technique, Some(2)));
+        assert_eq!(fils[1].status, Status::Termine);
+        assert_eq!(fils[1].get_notes()[0].auteur, ACTEUR);
+        assert_eq!(fils[2].pole, Pole::Legal);
+    }
+}
@@ -0,0 +1,27 @@
+use chrono::DateTime;
+use chrono_tz::Europe::Paris;
+use serenity::all::Timestamp;
+
+/// Séquences ayant un sens dans la syntaxe wikidot, pour les textes à coller sur le site.
+const SPECIAUX: [&str; 13] = ["[", "]", "**", "//", "__", "--", "^^", ",,", "{{", "}}", "@@", "##", "||"];
+
+/// Neutralise la syntaxe wikidot d’un texte en l’affichant tel quel (`@@…@@`) si besoin.
+pub fn echapper(texte: &str) -> String {
+    if SPECIAUX.iter().any(|special| texte.contains(special)) {
+        format!("@@{}@@", texte.replace("@@", "@ @"))
+    } else {
+        texte.to_string()
+    }
+}
+
+/// Lien vers une adresse. Les crochets, qui fermeraient le lien, sont remplacés par des parenthèses,
+/// et les doubles barres, qui couperaient une cellule de tableau, par une seule.
+pub fn lien(url: &str, texte: &str) -> String {
+    format!("[{url} {}]", texte.replace('[', "(").replace(']', ")").replace("||", "|"))
+}
+
+/// Date du jour à l’heure de Paris, au format `JJ/MM/AAAA`.
+pub fn date(timestamp: &Timestamp) -> String {
+    DateTime::from_timestamp(timestamp.unix_timestamp(), 0).unwrap_or_default()
+        .with_timezone(&Paris).format("%d/%m/%Y").to_string()
+}